@@ -1,9 +1,7 @@
 use redb_bincode::*;
 
 fn main() -> anyhow::Result<()> {
-    let db = redb::Database::create("test_db")?;
-
-    let db = Database::from(db);
+    let db = Database::in_memory()?;
 
     #[derive(bincode::Encode, bincode::Decode, Debug)]
     struct Something {
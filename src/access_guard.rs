@@ -1,60 +1,161 @@
 use std::fmt;
 use std::marker::PhantomData;
 
-use crate::{SortKey, SortOrder, BINCODE_CONFIG};
+use crate::encryption::{decrypt_value, TableCipher};
+use crate::{Bincode, CodecDecode, Error, SortKey, SortOrder, BINCODE_CONFIG};
 
-pub struct AccessGuard<'a, V, IV = &'static [u8]>
+/// Marks `B` as a borrowed counterpart of `Self` with the same `bincode`
+/// encoding, for [`AccessGuard::value_borrowed`]
+///
+/// Implement it for borrowed versions of your own types, e.g.
+/// `impl BorrowAs<DocumentRef<'_>> for Document {}`.
+pub trait BorrowAs<B> {}
+
+impl<T> BorrowAs<T> for T {}
+impl BorrowAs<&str> for String {}
+impl BorrowAs<&[u8]> for Vec<u8> {}
+impl BorrowAs<&str> for Box<str> {}
+impl BorrowAs<&[u8]> for Box<[u8]> {}
+
+pub struct AccessGuard<'a, V, IV = &'static [u8], C = Bincode>
 where
     IV: redb::Value + 'static,
 {
     inner: redb::AccessGuard<'a, IV>,
+    /// For values of encrypted tables, the cipher and the encoded key
+    cipher: Option<(&'a TableCipher, Vec<u8>)>,
     _v: PhantomData<V>,
+    _c: PhantomData<C>,
 }
 
-impl<'a, V> From<redb::AccessGuard<'a, &'static [u8]>> for AccessGuard<'a, V> {
+impl<'a, V, C> From<redb::AccessGuard<'a, &'static [u8]>> for AccessGuard<'a, V, &'static [u8], C> {
     fn from(inner: redb::AccessGuard<'a, &'static [u8]>) -> Self {
         Self {
             inner,
+            cipher: None,
             _v: PhantomData,
+            _c: PhantomData,
         }
     }
 }
 
-impl<'a, S, V> From<redb::AccessGuard<'a, SortKey<S>>> for AccessGuard<'a, V, SortKey<S>>
+impl<'a, S, V, C> From<redb::AccessGuard<'a, SortKey<S>>> for AccessGuard<'a, V, SortKey<S>, C>
 where
     S: SortOrder + fmt::Debug,
 {
     fn from(inner: redb::AccessGuard<'a, SortKey<S>>) -> Self {
         Self {
             inner,
+            cipher: None,
             _v: PhantomData,
+            _c: PhantomData,
         }
     }
 }
 
-impl<'a, V> AccessGuard<'a, V>
+impl<'a, V, C> AccessGuard<'a, V, &'static [u8], C> {
+    /// Decrypt the value of `key` with `cipher` before decoding it
+    pub(crate) fn with_cipher(mut self, cipher: Option<&'a TableCipher>, key: &[u8]) -> Self {
+        self.cipher = cipher.map(|cipher| (cipher, key.to_vec()));
+        self
+    }
+}
+
+impl<'a, V, C> AccessGuard<'a, V, &'static [u8], C>
 where
-    V: bincode::Decode,
+    C: CodecDecode<V>,
 {
     pub fn value(&self) -> V {
         self.value_try().expect("Invalid encoding")
     }
 
-    pub fn value_try(&self) -> Result<V, bincode::error::DecodeError> {
-        bincode::decode_from_slice(self.inner.value(), BINCODE_CONFIG).map(|v| v.0)
+    /// Like [`Self::value`], but returns an error instead of panicking
+    ///
+    /// Fails with [`Error::Decode`], or [`Error::Decryption`] for values of
+    /// encrypted tables.
+    pub fn value_try(&self) -> Result<V, Error> {
+        let raw = match &self.cipher {
+            Some((cipher, key)) => decrypt_value(Some(cipher), key, self.inner.value())?,
+            None => self.inner.value().into(),
+        };
+        Ok(C::decode(&raw)?)
+    }
+}
+
+impl<'a, V> AccessGuard<'a, V, &'static [u8], Bincode> {
+    /// Decode the value as `B`, borrowing from the stored bytes instead of
+    /// allocating
+    ///
+    /// `B` is a borrowed counterpart of `V` with the same encoding, e.g.
+    /// `&str` for `String`, or a struct with `&'g [u8]` fields, see
+    /// [`BorrowAs`].
+    pub fn value_borrowed<'g, B>(&'g self) -> B
+    where
+        V: BorrowAs<B>,
+        B: bincode::BorrowDecode<'g>,
+    {
+        self.value_borrowed_try().expect("Invalid encoding")
+    }
+
+    /// Like [`Self::value_borrowed`], but returns an error instead of panicking
+    ///
+    /// Values of encrypted tables can't be borrowed, and always fail with
+    /// [`Error::Decode`].
+    pub fn value_borrowed_try<'g, B>(&'g self) -> Result<B, Error>
+    where
+        V: BorrowAs<B>,
+        B: bincode::BorrowDecode<'g>,
+    {
+        if self.cipher.is_some() {
+            return Err(Error::Decode(bincode::error::DecodeError::Other(
+                "can't borrow from an encrypted value",
+            )));
+        }
+        Ok(bincode::borrow_decode_from_slice(self.inner.value(), BINCODE_CONFIG)?.0)
     }
 }
 
-impl<'a, V, S> AccessGuard<'a, V, SortKey<S>>
+impl<'a, V, S, C> AccessGuard<'a, V, SortKey<S>, C>
 where
-    V: bincode::Decode,
+    C: CodecDecode<V>,
     S: SortOrder + fmt::Debug,
 {
     pub fn value(&self) -> V {
         self.value_try().expect("Invalid encoding")
     }
 
-    pub fn value_try(&self) -> Result<V, bincode::error::DecodeError> {
-        bincode::decode_from_slice(self.inner.value(), BINCODE_CONFIG).map(|v| v.0)
+    /// Like [`Self::value`], but returns an error instead of panicking
+    ///
+    /// Fails with [`Error::Decode`].
+    pub fn value_try(&self) -> Result<V, Error> {
+        Ok(C::decode(self.inner.value())?)
+    }
+
+    /// The key as stored, encoded with `C`
+    pub(crate) fn encoded(&self) -> &[u8] {
+        self.inner.value()
+    }
+}
+
+impl<'a, V, S> AccessGuard<'a, V, SortKey<S>, Bincode>
+where
+    S: SortOrder + fmt::Debug,
+{
+    /// Decode the key as `B`, borrowing from the stored bytes instead of
+    /// allocating, see [`AccessGuard::value_borrowed`]
+    pub fn value_borrowed<'g, B>(&'g self) -> B
+    where
+        V: BorrowAs<B>,
+        B: bincode::BorrowDecode<'g>,
+    {
+        self.value_borrowed_try().expect("Invalid encoding")
+    }
+
+    pub fn value_borrowed_try<'g, B>(&'g self) -> Result<B, Error>
+    where
+        V: BorrowAs<B>,
+        B: bincode::BorrowDecode<'g>,
+    {
+        Ok(bincode::borrow_decode_from_slice(self.inner.value(), BINCODE_CONFIG)?.0)
     }
 }
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::{Database, Error, ReadTransaction, WriteTransaction};
+
+/// [`Database`] for use from async code
+///
+/// Transactions run on tokio's blocking thread pool. Writers wait for each
+/// other asynchronously, so at most one blocking thread is ever busy with
+/// (or waiting for) a write transaction.
+#[derive(Debug, Clone)]
+pub struct AsyncDatabase {
+    db: Arc<Database>,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl From<Database> for AsyncDatabase {
+    fn from(value: Database) -> Self {
+        Self {
+            db: Arc::new(value),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+impl AsyncDatabase {
+    /// The underlying [`Database`]
+    ///
+    /// Write transactions started on it directly are not serialized with
+    /// the ones of this [`AsyncDatabase`], and will block the calling thread.
+    pub fn as_sync(&self) -> &Database {
+        &self.db
+    }
+
+    /// Run `f` in a new read transaction, see [`Database::read`]
+    pub async fn read<T, E>(
+        &self,
+        f: impl FnOnce(&ReadTransaction) -> Result<T, E> + Send + 'static,
+    ) -> Result<T, E>
+    where
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        let db = self.db.clone();
+        spawn_blocking(move || db.read(f)).await
+    }
+
+    /// Run `f` in a new write transaction, see [`Database::write`]
+    pub async fn write<T, E>(
+        &self,
+        f: impl FnOnce(&mut WriteTransaction) -> Result<T, E> + Send + 'static,
+    ) -> Result<T, E>
+    where
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        let guard = self.write_lock.clone().lock_owned().await;
+        let db = self.db.clone();
+        spawn_blocking(move || {
+            let _guard = guard;
+            db.write(f)
+        })
+        .await
+    }
+}
+
+async fn spawn_blocking<T>(f: impl FnOnce() -> T + Send + 'static) -> T
+where
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(v) => v,
+        Err(e) => match e.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(e) => panic!("blocking task failed: {e}"),
+        },
+    }
+}
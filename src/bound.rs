@@ -0,0 +1,134 @@
+use std::borrow::Borrow;
+use std::ops;
+
+use crate::prefix::prefix_successor;
+use crate::{encode_bound, CodecEncode, Error, KeyPrefix, Lexicographical, PrefixCodec};
+
+/// One end of a range passed to [`Table::range_between`](crate::Table::range_between)
+///
+/// Unlike [`ops::RangeBounds`], the start and the end of the range don't
+/// have to be of the same type. Implemented for:
+///
+/// * `ops::Bound<&Q>`, where `K: Borrow<Q>`, e.g. `Bound::Excluded("abc")`
+///   for a `String` key,
+/// * [`OwnedBound`], for an owned key,
+/// * `..` ([`ops::RangeFull`]), for an unbounded end,
+/// * [`PrefixBound`], for keys starting with a prefix.
+pub trait KeyBound<K, S, KC> {
+    /// Encode as the start of a range into `buf`
+    ///
+    /// Returns `None` if no key can satisfy the bound, making the range
+    /// empty.
+    fn encode_start(&self, buf: &mut Vec<u8>) -> Result<Option<ops::Bound<()>>, Error>;
+
+    /// Encode as the end of a range into `buf`
+    fn encode_end(&self, buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error>;
+}
+
+impl<K, S, KC, Q> KeyBound<K, S, KC> for ops::Bound<&Q>
+where
+    K: Borrow<Q>,
+    KC: CodecEncode<Q>,
+    Q: ?Sized,
+{
+    fn encode_start(&self, buf: &mut Vec<u8>) -> Result<Option<ops::Bound<()>>, Error> {
+        encode_bound::<KC, Q>(*self, buf).map(Some)
+    }
+
+    fn encode_end(&self, buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error> {
+        encode_bound::<KC, Q>(*self, buf)
+    }
+}
+
+/// A range bound given by an owned key
+///
+/// Like `ops::Bound<&K>`, for keys computed on the fly, e.g.
+/// `OwnedBound::Excluded((group, next_ts))`. (`ops::Bound<K>` itself would
+/// conflict with the implementation for `ops::Bound<&Q>`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnedBound<K> {
+    Included(K),
+    Excluded(K),
+}
+
+impl<K> OwnedBound<K> {
+    fn as_bound(&self) -> ops::Bound<&K> {
+        match self {
+            OwnedBound::Included(key) => ops::Bound::Included(key),
+            OwnedBound::Excluded(key) => ops::Bound::Excluded(key),
+        }
+    }
+}
+
+impl<K, S, KC> KeyBound<K, S, KC> for OwnedBound<K>
+where
+    KC: CodecEncode<K>,
+{
+    fn encode_start(&self, buf: &mut Vec<u8>) -> Result<Option<ops::Bound<()>>, Error> {
+        encode_bound::<KC, K>(self.as_bound(), buf).map(Some)
+    }
+
+    fn encode_end(&self, buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error> {
+        encode_bound::<KC, K>(self.as_bound(), buf)
+    }
+}
+
+impl<K, S, KC> KeyBound<K, S, KC> for ops::RangeFull {
+    fn encode_start(&self, _buf: &mut Vec<u8>) -> Result<Option<ops::Bound<()>>, Error> {
+        Ok(Some(ops::Bound::Unbounded))
+    }
+
+    fn encode_end(&self, _buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error> {
+        Ok(ops::Bound::Unbounded)
+    }
+}
+
+/// A range bound given by a key prefix, see [`KeyPrefix`]
+///
+/// `Included` covers all keys starting with the prefix: as a start it is the
+/// first of them, as an end the last one. `Excluded` covers none of them: as a
+/// start the range begins after all of them, as an end it stops before the
+/// first one.
+///
+/// Only available for [`Lexicographical`] tables with a [`PrefixCodec`].
+#[derive(Debug, Clone, Copy)]
+pub enum PrefixBound<'a, P> {
+    Included(&'a P),
+    Excluded(&'a P),
+}
+
+impl<'a, K, KC, P> KeyBound<K, Lexicographical, KC> for PrefixBound<'a, P>
+where
+    K: KeyPrefix<P>,
+    KC: PrefixCodec + CodecEncode<P>,
+{
+    fn encode_start(&self, buf: &mut Vec<u8>) -> Result<Option<ops::Bound<()>>, Error> {
+        Ok(match *self {
+            PrefixBound::Included(prefix) => {
+                KC::encode_into(prefix, buf)?;
+                Some(ops::Bound::Included(()))
+            }
+            PrefixBound::Excluded(prefix) => {
+                KC::encode_into(prefix, buf)?;
+                match prefix_successor(buf) {
+                    ops::Bound::Excluded(()) => Some(ops::Bound::Included(())),
+                    // all keys after the prefix start with it
+                    _ => None,
+                }
+            }
+        })
+    }
+
+    fn encode_end(&self, buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error> {
+        Ok(match *self {
+            PrefixBound::Included(prefix) => {
+                KC::encode_into(prefix, buf)?;
+                prefix_successor(buf)
+            }
+            PrefixBound::Excluded(prefix) => {
+                KC::encode_into(prefix, buf)?;
+                ops::Bound::Excluded(())
+            }
+        })
+    }
+}
@@ -0,0 +1,175 @@
+use redb::{ReadableTable as _, StorageError, TableError, TableHandle as _};
+
+use crate::changes::RawChange;
+use crate::{Database, Error, Lexicographical, Memcomparable, SortKey, TableDefinition};
+
+const CHANGELOG: TableDefinition<u64, Record, Lexicographical, Memcomparable> =
+    TableDefinition::new("redb_bincode::changelog");
+/// On replicas, the sequence number of the next entry to apply
+const APPLIED: TableDefinition<(), u64> = TableDefinition::new("redb_bincode::changelog_applied");
+
+#[derive(bincode::Encode, bincode::Decode)]
+struct Record {
+    table: String,
+    key: Vec<u8>,
+    value: Option<Vec<u8>>,
+}
+
+/// An entry of the changelog of a database, see
+/// [`Builder::set_changelog`](crate::Builder::set_changelog)
+///
+/// Keys and values are stored as encoded (and, for encrypted tables,
+/// encrypted) in the table.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct ChangelogEntry {
+    pub seq: u64,
+    pub table: String,
+    pub key: Vec<u8>,
+    /// The new value, `None` if the key was removed
+    pub value: Option<Vec<u8>>,
+}
+
+/// Append `changes` to the changelog, as part of `tx`
+pub(crate) fn append(
+    tx: &redb::WriteTransaction,
+    changes: &[RawChange],
+) -> Result<(), StorageError> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut log = tx
+        .open_table(redb::TableDefinition::<SortKey<Lexicographical>, &[u8]>::new(CHANGELOG.name()))
+        .map_err(expect_storage)?;
+    let first_seq = match log.last()? {
+        Some((k, _)) => decode_seq(k.value()) + 1,
+        None => 0,
+    };
+    let mut key_buf = vec![];
+    let mut value_buf = vec![];
+    for (seq, change) in (first_seq..).zip(changes) {
+        key_buf.clear();
+        value_buf.clear();
+        crate::memcomparable::Encode::encode(&seq, &mut key_buf);
+        bincode::encode_into_std_write(
+            Record {
+                table: change.table.to_string(),
+                key: change.key.clone(),
+                value: change.new.clone(),
+            },
+            &mut value_buf,
+            crate::BINCODE_CONFIG,
+        )
+        .expect("encoding can't fail");
+        log.insert(&key_buf[..], &value_buf[..])?;
+    }
+    Ok(())
+}
+
+/// Whether `db` has a changelog, i.e. was ever opened with one enabled
+pub(crate) fn exists(db: &redb::Database) -> Result<bool, Error> {
+    let tx = db.begin_read()?;
+    let exists = tx
+        .list_tables()?
+        .any(|handle| handle.name() == CHANGELOG.name());
+    Ok(exists)
+}
+
+fn decode_seq(mut raw: &[u8]) -> u64 {
+    <u64 as crate::memcomparable::Decode>::decode(&mut raw).expect("Invalid encoding")
+}
+
+fn expect_storage(e: TableError) -> StorageError {
+    match e {
+        TableError::Storage(e) => e,
+        e => panic!("changelog table: {e}"),
+    }
+}
+
+impl Database {
+    /// Read up to `limit` changelog entries, starting with sequence number
+    /// `from`
+    pub fn read_changelog(&self, from: u64, limit: usize) -> Result<Vec<ChangelogEntry>, Error> {
+        let tx = self.begin_read()?;
+        let log = match tx.open_table(&CHANGELOG) {
+            Ok(log) => log,
+            Err(Error::Table(TableError::TableDoesNotExist(_))) => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        log.try_range(from..)?
+            .take(limit)
+            .map(|entry| {
+                let (seq, record) = entry?;
+                let record = record.value_try()?;
+                Ok(ChangelogEntry {
+                    seq: seq.value_try()?,
+                    table: record.table,
+                    key: record.key,
+                    value: record.value,
+                })
+            })
+            .collect()
+    }
+
+    /// Remove all changelog entries before sequence number `before`, e.g.
+    /// once all replicas applied them
+    ///
+    /// The last entry is always kept, as the sequence continues after it.
+    pub fn truncate_changelog(&self, before: u64) -> Result<(), Error> {
+        self.write(|tx| {
+            let mut log = tx.open_table_untracked(&CHANGELOG)?;
+            let Some(last) = log.last()?.map(|(k, _)| k.value()) else {
+                return Ok(());
+            };
+            log.try_retain_in(..before.min(last), |_, _| false)
+        })
+    }
+
+    /// Apply entries read with [`Self::read_changelog`] from another
+    /// database, in a single transaction
+    ///
+    /// Entries that were already applied are skipped, and a gap in the
+    /// sequence numbers fails with [`Error::ChangelogGap`]. Tables are
+    /// written as raw bytes, so encrypted tables need the same cipher on both
+    /// databases. Applied changes are not recorded in this database's own
+    /// changelog.
+    pub fn apply_changelog(&self, entries: &[ChangelogEntry]) -> Result<(), Error> {
+        self.write(|tx| {
+            let mut applied = tx.open_table_untracked(&APPLIED)?;
+            let mut next = applied.try_get(&())?.map(|v| v.value()).unwrap_or(0);
+            for entry in entries {
+                if entry.seq < next {
+                    continue;
+                }
+                if entry.seq != next {
+                    return Err(Error::ChangelogGap {
+                        expected: next,
+                        found: entry.seq,
+                    });
+                }
+                let mut table = tx
+                    .as_raw()
+                    .open_table(
+                        redb::TableDefinition::<SortKey<Lexicographical>, &[u8]>::new(&entry.table),
+                    )?;
+                match &entry.value {
+                    Some(value) => table.insert(&entry.key[..], &value[..])?,
+                    None => table.remove(&entry.key[..])?,
+                };
+                next += 1;
+            }
+            applied.try_insert(&(), &next)?;
+            Ok(())
+        })
+    }
+
+    /// Sequence number of the next changelog entry [`Self::apply_changelog`]
+    /// expects
+    pub fn applied_changelog(&self) -> Result<u64, Error> {
+        let tx = self.begin_read()?;
+        match tx.open_table(&APPLIED) {
+            Ok(applied) => Ok(applied.try_get(&())?.map(|v| v.value()).unwrap_or(0)),
+            Err(Error::Table(TableError::TableDoesNotExist(_))) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
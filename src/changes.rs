@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::{encryption, CodecDecode, Error, TableCipher};
+
+/// A change made to a table in a committed transaction, see
+/// [`Database::on_change`](crate::Database::on_change)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<K, V> {
+    Insert { key: K, value: V },
+    Overwrite { key: K, old: V, new: V },
+    Remove { key: K, value: V },
+}
+
+/// A change as stored, with values possibly encrypted
+#[derive(Debug, Clone)]
+pub(crate) struct RawChange {
+    pub(crate) table: Arc<str>,
+    pub(crate) key: Vec<u8>,
+    pub(crate) old: Option<Vec<u8>>,
+    pub(crate) new: Option<Vec<u8>>,
+}
+
+impl RawChange {
+    pub(crate) fn decode<K, V, KC, VC>(
+        &self,
+        cipher: Option<&TableCipher>,
+    ) -> Result<Change<K, V>, Error>
+    where
+        KC: CodecDecode<K>,
+        VC: CodecDecode<V>,
+    {
+        let decode_value = |raw: &[u8]| -> Result<V, Error> {
+            Ok(VC::decode(&encryption::decrypt_value(
+                cipher, &self.key, raw,
+            )?)?)
+        };
+        let key = KC::decode(&self.key)?;
+        Ok(match (&self.old, &self.new) {
+            (None, Some(new)) => Change::Insert {
+                key,
+                value: decode_value(new)?,
+            },
+            (Some(old), Some(new)) => Change::Overwrite {
+                key,
+                old: decode_value(old)?,
+                new: decode_value(new)?,
+            },
+            (Some(old), None) => Change::Remove {
+                key,
+                value: decode_value(old)?,
+            },
+            (None, None) => unreachable!("change without values"),
+        })
+    }
+}
+
+/// Records the changes made through a [`Table`](crate::Table) while change
+/// tracking is enabled, see
+/// [`WriteTransaction::set_track_changes`](crate::WriteTransaction::set_track_changes)
+#[derive(Clone)]
+pub(crate) struct ChangeRecorder<'a> {
+    log: &'a Mutex<Vec<RawChange>>,
+    table: Arc<str>,
+}
+
+impl<'a> ChangeRecorder<'a> {
+    pub(crate) fn new(log: &'a Mutex<Vec<RawChange>>, table: &str) -> Self {
+        Self {
+            log,
+            table: table.into(),
+        }
+    }
+
+    pub(crate) fn record(&self, key: &[u8], old: Option<&[u8]>, new: Option<&[u8]>) {
+        self.log
+            .lock()
+            .expect("change log poisoned")
+            .push(RawChange {
+                table: self.table.clone(),
+                key: key.to_vec(),
+                old: old.map(<[u8]>::to_vec),
+                new: new.map(<[u8]>::to_vec),
+            });
+    }
+}
+
+/// Id of a listener registered with [`Database::on_change`](crate::Database::on_change)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+type RawListener = Arc<dyn Fn(&[RawChange]) + Send + Sync>;
+
+/// Listeners of a [`Database`](crate::Database), shared with its write
+/// transactions
+#[derive(Default)]
+pub(crate) struct Listeners {
+    next_id: AtomicU64,
+    listeners: RwLock<Vec<(ListenerId, RawListener)>>,
+}
+
+impl Listeners {
+    pub(crate) fn add(
+        &self,
+        listener: impl Fn(&[RawChange]) + Send + Sync + 'static,
+    ) -> ListenerId {
+        let id = ListenerId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.listeners
+            .write()
+            .expect("listeners poisoned")
+            .push((id, Arc::new(listener)));
+        id
+    }
+
+    pub(crate) fn remove(&self, id: ListenerId) -> bool {
+        let mut listeners = self.listeners.write().expect("listeners poisoned");
+        let len = listeners.len();
+        listeners.retain(|(listener_id, _)| *listener_id != id);
+        listeners.len() != len
+    }
+
+    pub(crate) fn deliver(&self, changes: &[RawChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        // don't hold the lock, so listeners can (un)register listeners
+        let listeners = self
+            .listeners
+            .read()
+            .expect("listeners poisoned")
+            .iter()
+            .map(|(_, listener)| listener.clone())
+            .collect::<Vec<_>>();
+        for listener in listeners {
+            listener(changes);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use bincode::error::{DecodeError, EncodeError};
+
+use crate::{memcomparable, BINCODE_CONFIG};
+
+/// Encoding side of a codec: how a `T` is turned into bytes stored in `redb`
+pub trait CodecEncode<T: ?Sized> {
+    fn encode_into(value: &T, buf: &mut Vec<u8>) -> Result<(), EncodeError>;
+}
+
+/// Decoding side of a codec: how bytes stored in `redb` are turned back into a
+/// `T`
+pub trait CodecDecode<T> {
+    fn decode(bytes: &[u8]) -> Result<T, DecodeError>;
+}
+
+/// A codec that can both encode and decode a `T`
+///
+/// Implemented automatically for anything implementing [`CodecEncode`] and
+/// [`CodecDecode`].
+pub trait Codec<T>: CodecEncode<T> + CodecDecode<T> {}
+
+impl<T, C> Codec<T> for C where C: CodecEncode<T> + CodecDecode<T> {}
+
+/// Default codec: big-endian, variable int `bincode` ([`BINCODE_CONFIG`])
+///
+/// Compact, but the byte order of encoded keys does not match the `Ord` of
+/// signed integers, strings or other variable-length types.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+impl<T> CodecEncode<T> for Bincode
+where
+    T: bincode::Encode + ?Sized,
+{
+    fn encode_into(value: &T, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        bincode::encode_into_std_write(value, buf, BINCODE_CONFIG)?;
+        Ok(())
+    }
+}
+
+impl<T> CodecDecode<T> for Bincode
+where
+    T: bincode::Decode,
+{
+    fn decode(bytes: &[u8]) -> Result<T, DecodeError> {
+        bincode::decode_from_slice(bytes, BINCODE_CONFIG).map(|v| v.0)
+    }
+}
+
+/// Order-preserving key codec
+///
+/// Encoded bytes compare (lexicographically) the same way as the `Ord` of the
+/// Rust type, so [`Lexicographical`](crate::Lexicographical) tables iterate
+/// and `range` in the natural order. See [`memcomparable`] for the format and
+/// the supported types.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Memcomparable;
+
+impl<T> CodecEncode<T> for Memcomparable
+where
+    T: memcomparable::Encode + ?Sized,
+{
+    fn encode_into(value: &T, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        value.encode(buf);
+        Ok(())
+    }
+}
+
+impl<T> CodecDecode<T> for Memcomparable
+where
+    T: memcomparable::Decode,
+{
+    fn decode(mut bytes: &[u8]) -> Result<T, DecodeError> {
+        let value = T::decode(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(DecodeError::Other(
+                "trailing bytes after memcomparable value",
+            ));
+        }
+        Ok(value)
+    }
+}
+
+/// `serde`-based codec, using `bincode`'s serde mode with [`BINCODE_CONFIG`]
+///
+/// For types that implement `serde::Serialize`/`serde::Deserialize`, but not
+/// `bincode::Encode`/`bincode::Decode`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeSerde;
+
+#[cfg(feature = "serde")]
+impl<T> CodecEncode<T> for BincodeSerde
+where
+    T: serde::Serialize + ?Sized,
+{
+    fn encode_into(value: &T, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        bincode::serde::encode_into_std_write(value, buf, BINCODE_CONFIG)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> CodecDecode<T> for BincodeSerde
+where
+    T: serde::de::DeserializeOwned,
+{
+    fn decode(bytes: &[u8]) -> Result<T, DecodeError> {
+        bincode::serde::decode_from_slice(bytes, BINCODE_CONFIG).map(|v| v.0)
+    }
+}
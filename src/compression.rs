@@ -0,0 +1,179 @@
+use std::marker::PhantomData;
+
+use bincode::error::{DecodeError, EncodeError};
+
+use crate::{Bincode, CodecDecode, CodecEncode};
+
+/// Start of values stored compressed, followed by the algorithm's
+/// [`Compression::TAG`]
+///
+/// `0xff` never starts a `bincode` varint.
+const MAGIC: [u8; 4] = *b"\xffRBC";
+/// Tag of values stored uncompressed, but starting with [`MAGIC`] themselves
+const TAG_NONE: u8 = 0;
+
+/// A compression algorithm usable with [`Compressed`]
+pub trait Compression {
+    /// Header byte identifying values compressed with this algorithm
+    ///
+    /// 0 is reserved, and 1 and 2 are used by [`Zstd`] and [`Lz4`].
+    ///
+    /// ```compile_fail
+    /// # use redb_bincode::{CodecEncode, Compressed, Compression, DecodeError};
+    /// struct Reserved;
+    ///
+    /// impl Compression for Reserved {
+    ///     const TAG: u8 = 0;
+    ///
+    ///     fn compress(input: &[u8]) -> Vec<u8> {
+    ///         input.to_vec()
+    ///     }
+    ///
+    ///     fn decompress(input: &[u8], _max_size: usize) -> Result<Vec<u8>, DecodeError> {
+    ///         Ok(input.to_vec())
+    ///     }
+    /// }
+    ///
+    /// let mut buf = vec![];
+    /// <Compressed<Reserved> as CodecEncode<u64>>::encode_into(&1, &mut buf).unwrap();
+    /// ```
+    const TAG: u8;
+
+    fn compress(input: &[u8]) -> Vec<u8>;
+
+    /// Decompress `input`, failing if the result would exceed `max_size`
+    /// bytes
+    fn decompress(input: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError>;
+}
+
+/// `zstd` compression, at the default level
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Zstd;
+
+#[cfg(feature = "zstd")]
+impl Compression for Zstd {
+    const TAG: u8 = 1;
+
+    fn compress(input: &[u8]) -> Vec<u8> {
+        zstd::encode_all(input, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .expect("compressing in memory can't fail")
+    }
+
+    fn decompress(input: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+        use std::io::Read as _;
+
+        let failed = |e| DecodeError::OtherString(format!("zstd decompression failed: {e}"));
+        let mut output = vec![];
+        zstd::Decoder::new(input)
+            .map_err(failed)?
+            .take(max_size as u64 + 1)
+            .read_to_end(&mut output)
+            .map_err(failed)?;
+        if max_size < output.len() {
+            return Err(DecodeError::Other("decompressed value too large"));
+        }
+        Ok(output)
+    }
+}
+
+/// `lz4` compression: faster, but compressing less than [`Zstd`]
+#[cfg(feature = "lz4")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lz4;
+
+#[cfg(feature = "lz4")]
+impl Compression for Lz4 {
+    const TAG: u8 = 2;
+
+    fn compress(input: &[u8]) -> Vec<u8> {
+        lz4_flex::compress_prepend_size(input)
+    }
+
+    fn decompress(input: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+        let failed = |e| DecodeError::OtherString(format!("lz4 decompression failed: {e}"));
+        let (size, input) = lz4_flex::block::uncompressed_size(input).map_err(failed)?;
+        if max_size < size {
+            return Err(DecodeError::Other("decompressed value too large"));
+        }
+        lz4_flex::decompress(input, size).map_err(failed)
+    }
+}
+
+/// Value codec compressing the output of the codec `C` with `A`
+///
+/// Compressed values start with a header recording the algorithm, so a table
+/// can switch algorithms without rewriting existing values, as long as the
+/// features of all built-in algorithms used are enabled (values of other
+/// algorithms are only readable with `A`). Values shorter than
+/// `MIN_SIZE` bytes (or not getting any smaller) are stored as plain `C`, and
+/// values without the header are decoded with `C`, so existing tables of
+/// plain `C` can start using it right away.
+///
+/// Values larger than `MAX_SIZE` bytes are stored uncompressed, and
+/// decompressing more than that fails, to bound the memory a corrupt value
+/// can make a decode allocate.
+pub struct Compressed<A, C = Bincode, const MIN_SIZE: usize = 64, const MAX_SIZE: usize = 67108864>
+{
+    _a: PhantomData<A>,
+    _c: PhantomData<C>,
+}
+
+impl<T, A, C, const MIN_SIZE: usize, const MAX_SIZE: usize> CodecEncode<T>
+    for Compressed<A, C, MIN_SIZE, MAX_SIZE>
+where
+    T: ?Sized,
+    A: Compression,
+    C: CodecEncode<T>,
+{
+    fn encode_into(value: &T, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        const { assert!(A::TAG != TAG_NONE, "compression tag 0 is reserved") };
+        let start = buf.len();
+        C::encode_into(value, buf)?;
+
+        let encoded = &buf[start..];
+        if (MIN_SIZE..=MAX_SIZE).contains(&encoded.len()) {
+            let compressed = A::compress(encoded);
+            if compressed.len() + MAGIC.len() + 1 < encoded.len() {
+                buf.truncate(start);
+                buf.extend_from_slice(&MAGIC);
+                buf.push(A::TAG);
+                buf.extend_from_slice(&compressed);
+                return Ok(());
+            }
+        }
+        if encoded.starts_with(&MAGIC) {
+            // escape, so it's not mistaken for a compressed value
+            buf.splice(start..start, MAGIC.into_iter().chain([TAG_NONE]));
+        }
+        Ok(())
+    }
+}
+
+impl<T, A, C, const MIN_SIZE: usize, const MAX_SIZE: usize> CodecDecode<T>
+    for Compressed<A, C, MIN_SIZE, MAX_SIZE>
+where
+    A: Compression,
+    C: CodecDecode<T>,
+{
+    fn decode(bytes: &[u8]) -> Result<T, DecodeError> {
+        const { assert!(A::TAG != TAG_NONE, "compression tag 0 is reserved") };
+        let Some(rest) = bytes.strip_prefix(&MAGIC) else {
+            return C::decode(bytes);
+        };
+        let (&tag, payload) = rest
+            .split_first()
+            .ok_or(DecodeError::UnexpectedEnd { additional: 1 })?;
+        match tag {
+            TAG_NONE => C::decode(payload),
+            tag if tag == A::TAG => C::decode(&A::decompress(payload, MAX_SIZE)?),
+            #[cfg(feature = "zstd")]
+            Zstd::TAG => C::decode(&Zstd::decompress(payload, MAX_SIZE)?),
+            #[cfg(feature = "lz4")]
+            Lz4::TAG => C::decode(&Lz4::decompress(payload, MAX_SIZE)?),
+            tag => Err(DecodeError::OtherString(format!(
+                "unknown compression: {tag}"
+            ))),
+        }
+    }
+}
@@ -1,33 +1,258 @@
+use std::fmt;
+use std::fs::File;
 use std::path::Path;
+use std::sync::Arc;
 
-use redb::{DatabaseError, TransactionError};
+use redb::{DatabaseError, RepairSession, StorageBackend, TransactionError};
 
 use super::tx::{ReadTransaction, WriteTransaction};
-use crate::tx;
+use crate::changes::Listeners;
+use crate::{changelog, tx, Change, Cipher, Codec, Error, ListenerId, TableDefinition};
 
-#[derive(Debug)]
-pub struct Database(redb::Database);
+pub struct Database {
+    inner: redb::Database,
+    cipher: Option<Arc<dyn Cipher>>,
+    listeners: Arc<Listeners>,
+    changelog: bool,
+}
+
+impl fmt::Debug for Database {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database")
+            .field("inner", &self.inner)
+            .field("encrypted", &self.cipher.is_some())
+            .field("changelog", &self.changelog)
+            .finish()
+    }
+}
 
 impl Database {
     pub fn create(path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
-        Ok(Self(redb::Database::create(path)?))
+        Ok(Self::from(redb::Database::create(path)?))
     }
 
     pub fn open(path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
-        Ok(Self(redb::Database::open(path)?))
+        Ok(Self::from(redb::Database::open(path)?))
     }
 
+    /// Create a database backed only by memory, e.g. for tests
+    pub fn in_memory() -> Result<Database, DatabaseError> {
+        Builder::new().create_with_backend(redb::backends::InMemoryBackend::new())
+    }
+
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    pub fn as_raw(&self) -> &redb::Database {
+        &self.inner
+    }
+
+    #[allow(clippy::result_large_err)]
     pub fn begin_read(&self) -> Result<tx::ReadTransaction, TransactionError> {
-        Ok(ReadTransaction::from(self.0.begin_read()?))
+        Ok(ReadTransaction::from(self.inner.begin_read()?).with_cipher(self.cipher.clone()))
     }
 
+    #[allow(clippy::result_large_err)]
     pub fn begin_write(&self) -> Result<tx::WriteTransaction, TransactionError> {
-        Ok(WriteTransaction::from(self.0.begin_write()?))
+        Ok(WriteTransaction::from(self.inner.begin_write()?)
+            .with_cipher(self.cipher.clone())
+            .with_listeners(self.listeners.clone())
+            .with_changelog(self.changelog))
+    }
+
+    /// Call `listener` with the changes made to `table_def` by every
+    /// committed write transaction that tracks changes
+    ///
+    /// See [`WriteTransaction::set_track_changes`]. Listeners are called on
+    /// the committing thread, after the commit succeeded, with all changes to
+    /// the table in the order they were made. Changes whose keys or values
+    /// fail to decode (or decrypt) are passed as errors, in their place.
+    pub fn on_change<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+        listener: impl Fn(&[Result<Change<K, V>, Error>]) + Send + Sync + 'static,
+    ) -> Result<ListenerId, Error>
+    where
+        K: 'static,
+        V: 'static,
+        S: 'static,
+        KC: Codec<K> + 'static,
+        VC: Codec<V> + 'static,
+    {
+        let table = table_def.name().to_owned();
+        let cipher = table_def.cipher(&self.cipher)?;
+        Ok(self.listeners.add(move |changes| {
+            let changes = changes
+                .iter()
+                .filter(|change| *change.table == table)
+                .map(|change| change.decode::<K, V, KC, VC>(cipher.as_deref()))
+                .collect::<Vec<_>>();
+            if !changes.is_empty() {
+                listener(&changes);
+            }
+        }))
+    }
+
+    /// Unregister a listener, returning `true` if it was registered
+    pub fn remove_change_listener(&self, id: ListenerId) -> bool {
+        self.listeners.remove(id)
+    }
+
+    /// Run `f` in a new read transaction
+    pub fn read<T, E>(&self, f: impl FnOnce(&ReadTransaction) -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<Error>,
+    {
+        let tx = self.begin_read().map_err(Error::from)?;
+        f(&tx)
+    }
+
+    /// Run `f` in a new write transaction, committing it if `f` returns `Ok`
+    /// and aborting it otherwise
+    ///
+    /// A failed commit can't simply be re-run: after any I/O error `redb`
+    /// fails all further transactions with
+    /// [`StorageError::PreviousIo`](redb::StorageError::PreviousIo) until the
+    /// database is reopened.
+    pub fn write<T, E>(&self, f: impl FnOnce(&mut WriteTransaction) -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<Error>,
+    {
+        let mut tx = self.begin_write().map_err(Error::from)?;
+        match f(&mut tx) {
+            Ok(v) => {
+                tx.commit().map_err(Error::from)?;
+                Ok(v)
+            }
+            Err(e) => {
+                // `f`'s error is the more relevant one
+                let _ = tx.abort();
+                Err(e)
+            }
+        }
     }
 }
 
 impl From<redb::Database> for Database {
     fn from(value: redb::Database) -> Self {
-        Self(value)
+        Builder::new().build(value)
+    }
+}
+
+/// Configuration for opening or creating a [`Database`]
+///
+/// See [`redb::Builder`].
+pub struct Builder {
+    inner: redb::Builder,
+    cipher: Option<Arc<dyn Cipher>>,
+    changelog: bool,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::from(redb::Builder::new())
+    }
+
+    /// Set the amount of memory (in bytes) used for caching data
+    pub fn set_cache_size(&mut self, bytes: usize) -> &mut Self {
+        self.inner.set_cache_size(bytes);
+        self
+    }
+
+    /// Set a callback invoked periodically while repairing a database that
+    /// was not shut down cleanly
+    pub fn set_repair_callback(
+        &mut self,
+        callback: impl Fn(&mut RepairSession) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.inner.set_repair_callback(callback);
+        self
+    }
+
+    /// Set the [`Cipher`] used for the values of encrypted tables, see
+    /// [`TableDefinition::encrypted`](crate::TableDefinition::encrypted)
+    pub fn set_cipher(&mut self, cipher: impl Cipher) -> &mut Self {
+        self.cipher = Some(Arc::new(cipher));
+        self
+    }
+
+    /// Encrypt the values of encrypted tables with ChaCha20-Poly1305 using
+    /// `key`
+    #[cfg(feature = "encryption")]
+    pub fn set_encryption_key(&mut self, key: &[u8; 32]) -> &mut Self {
+        self.set_cipher(crate::ChaCha20Poly1305::new(key))
+    }
+
+    /// Keep a changelog of all changes made through
+    /// [`WriteTransaction::open_table`], e.g. for replication (defaults to
+    /// disabled)
+    ///
+    /// Every committed change is appended to an internal table in the same
+    /// transaction, with consecutive sequence numbers. See
+    /// [`Database::read_changelog`] and [`Database::apply_changelog`].
+    ///
+    /// Like change tracking (see [`WriteTransaction::set_track_changes`]),
+    /// this does not cover raw access. Operations it can't record fail with
+    /// [`Error::NotLogged`] instead:
+    /// opening multimap or indexed tables, or tables with a sort order other
+    /// than [`Lexicographical`](crate::Lexicographical) for writing, and
+    /// deleting or renaming tables, and restoring persistent savepoints.
+    ///
+    /// A database that has a changelog keeps it: it is also appended to when
+    /// the database is opened without enabling it, so replicas don't miss
+    /// changes.
+    pub fn set_changelog(&mut self, enabled: bool) -> &mut Self {
+        self.changelog = enabled;
+        self
+    }
+
+    pub fn create(&self, path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
+        Ok(self.build(self.inner.create(path)?))
+    }
+
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
+        Ok(self.build(self.inner.open(path)?))
+    }
+
+    pub fn create_file(&self, file: File) -> Result<Database, DatabaseError> {
+        Ok(self.build(self.inner.create_file(file)?))
+    }
+
+    /// Create a database on a custom storage backend, e.g.
+    /// [`redb::backends::InMemoryBackend`]
+    pub fn create_with_backend(
+        &self,
+        backend: impl StorageBackend,
+    ) -> Result<Database, DatabaseError> {
+        Ok(self.build(self.inner.create_with_backend(backend)?))
+    }
+
+    fn build(&self, inner: redb::Database) -> Database {
+        // if the database can't be read, it can't be written either, so
+        // keeping a changelog is harmless
+        let changelog = self.changelog || changelog::exists(&inner).unwrap_or(true);
+        Database {
+            inner,
+            cipher: self.cipher.clone(),
+            listeners: Arc::default(),
+            changelog,
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<redb::Builder> for Builder {
+    fn from(value: redb::Builder) -> Self {
+        Self {
+            inner: value,
+            cipher: None,
+            changelog: false,
+        }
     }
 }
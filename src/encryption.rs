@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Authenticated encryption of values at rest
+///
+/// Set with [`Builder::set_cipher`](crate::Builder::set_cipher) and used for
+/// tables defined with [`TableDefinition::encrypted`](crate::TableDefinition::encrypted).
+///
+/// `aad` (associated data) must be authenticated together with the value, but
+/// is not stored: it identifies the table and key of the value, so values
+/// can't be moved to another key or table.
+pub trait Cipher: Send + Sync + 'static {
+    /// Append the encrypted `plaintext` to `out`
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8], out: &mut Vec<u8>);
+
+    fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, DecryptionError>;
+}
+
+/// A value could not be decrypted, because it was corrupted, tampered with,
+/// or encrypted with a different key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("value failed to decrypt")]
+pub struct DecryptionError;
+
+/// ChaCha20-Poly1305 [`Cipher`], storing a random nonce with every value
+#[cfg(feature = "encryption")]
+pub struct ChaCha20Poly1305(chacha20poly1305::ChaCha20Poly1305);
+
+#[cfg(feature = "encryption")]
+impl ChaCha20Poly1305 {
+    const NONCE_SIZE: usize = 12;
+
+    pub fn new(key: &[u8; 32]) -> Self {
+        use chacha20poly1305::KeyInit as _;
+
+        Self(chacha20poly1305::ChaCha20Poly1305::new(key.into()))
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Cipher for ChaCha20Poly1305 {
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8], out: &mut Vec<u8>) {
+        use chacha20poly1305::aead::{Aead as _, AeadCore as _, OsRng, Payload};
+
+        let nonce = chacha20poly1305::ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("encrypting in memory can't fail");
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, DecryptionError> {
+        use chacha20poly1305::aead::{Aead as _, Payload};
+
+        if ciphertext.len() < Self::NONCE_SIZE {
+            return Err(DecryptionError);
+        }
+        let (nonce, ciphertext) = ciphertext.split_at(Self::NONCE_SIZE);
+        self.0
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| DecryptionError)
+    }
+}
+
+/// The [`Cipher`] of an encrypted table, binding values to the table's name
+/// and their key
+pub(crate) struct TableCipher {
+    cipher: Arc<dyn Cipher>,
+    table: String,
+}
+
+impl TableCipher {
+    pub(crate) fn new(cipher: Arc<dyn Cipher>, table: &str) -> Self {
+        Self {
+            cipher,
+            table: table.to_owned(),
+        }
+    }
+
+    /// The table name (length-prefixed, so it can't run into the key),
+    /// followed by the encoded `key`
+    fn aad(&self, key: &[u8]) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(4 + self.table.len() + key.len());
+        aad.extend_from_slice(&(self.table.len() as u32).to_be_bytes());
+        aad.extend_from_slice(self.table.as_bytes());
+        aad.extend_from_slice(key);
+        aad
+    }
+
+    pub(crate) fn encrypt(&self, key: &[u8], plaintext: &[u8], out: &mut Vec<u8>) {
+        self.cipher.encrypt(plaintext, &self.aad(key), out);
+    }
+
+    pub(crate) fn decrypt(
+        &self,
+        key: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, DecryptionError> {
+        self.cipher.decrypt(ciphertext, &self.aad(key))
+    }
+}
+
+/// Encrypt the value encoded in `buf` in place, if `cipher` is set
+pub(crate) fn encrypt_value_buf(cipher: Option<&TableCipher>, key: &[u8], buf: &mut Vec<u8>) {
+    if let Some(cipher) = cipher {
+        let plaintext = std::mem::take(buf);
+        cipher.encrypt(key, &plaintext, buf);
+    }
+}
+
+/// Decrypt the raw stored value of `key`, if `cipher` is set
+pub(crate) fn decrypt_value<'a>(
+    cipher: Option<&TableCipher>,
+    key: &[u8],
+    raw: &'a [u8],
+) -> Result<Cow<'a, [u8]>, DecryptionError> {
+    Ok(match cipher {
+        Some(cipher) => Cow::Owned(cipher.decrypt(key, raw)?),
+        None => Cow::Borrowed(raw),
+    })
+}
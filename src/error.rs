@@ -0,0 +1,83 @@
+use std::io;
+
+use bincode::error::{DecodeError, EncodeError};
+use redb::{
+    CommitError, DatabaseError, SavepointError, StorageError, TableError, TransactionError,
+};
+
+use crate::DecryptionError;
+
+/// Crate-level error, covering everything that can go wrong when working with
+/// the database
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("table error: {0}")]
+    Table(#[from] TableError),
+    #[error("transaction error: {0}")]
+    Transaction(#[from] Box<TransactionError>),
+    #[error("commit error: {0}")]
+    Commit(#[from] CommitError),
+    #[error("savepoint error: {0}")]
+    Savepoint(#[from] SavepointError),
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+    #[error("encoding error: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("decoding error: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("decryption error: {0}")]
+    Decryption(#[from] DecryptionError),
+    /// A value's key in the named unique index is already used by another key
+    #[error("unique index violation: {0}")]
+    UniqueIndex(String),
+    /// The named index was not opened with the indexed table it was used on
+    #[error("index {0} was not opened with this table")]
+    IndexNotOpened(String),
+    /// Changelog entries were skipped, see
+    /// [`Database::apply_changelog`](crate::Database::apply_changelog)
+    #[error("changelog gap: expected entry {expected}, found {found}")]
+    ChangelogGap { expected: u64, found: u64 },
+    /// The operation can't be recorded in the changelog, so it is refused
+    /// while the database keeps one, see
+    /// [`Builder::set_changelog`](crate::Builder::set_changelog)
+    #[error("not supported with a changelog: {0}")]
+    NotLogged(String),
+    /// The named table is encrypted, but the database has no cipher set
+    #[error("table {0} is encrypted, but the database has no cipher set")]
+    MissingCipher(String),
+    /// [`ReadableTable::page`](crate::ReadableTable::page) was called with a
+    /// limit of 0
+    #[error("page limit must be at least 1")]
+    ZeroPageLimit,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// The stream passed to [`Database::import`](crate::Database::import) is
+    /// not a valid export
+    #[error("corrupt export: {0}")]
+    CorruptExport(&'static str),
+    /// The named table has a custom sort order, which
+    /// [`Database::export`](crate::Database::export) doesn't support
+    #[error("table {0} has a custom sort order, which exports don't support")]
+    UnsupportedSortOrder(String),
+}
+
+impl From<TransactionError> for Error {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(Box::new(value))
+    }
+}
+
+impl Error {
+    /// Unwrap the [`StorageError`] for the non-`try_` methods, panicking on
+    /// encoding errors like they always did
+    pub(crate) fn expect_storage(self) -> StorageError {
+        match self {
+            Error::Storage(e) => e,
+            Error::Encode(e) => panic!("encoding can't fail: {e}"),
+            Error::Decode(e) => panic!("Invalid encoding: {e}"),
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use std::any::TypeId;
+use std::io::{self, Read as _};
+
+use redb::{
+    MultimapTableHandle as _, ReadableMultimapTable as _, ReadableTable as _, TableError,
+    TableHandle as _,
+};
+
+use crate::{Database, Error, Lexicographical, MultimapTableDefinition, SortKey, TableDefinition};
+
+type RawTableDefinition<'a> = redb::TableDefinition<'a, SortKey<Lexicographical>, &'static [u8]>;
+type RawMultimapTableDefinition<'a> =
+    redb::MultimapTableDefinition<'a, SortKey<Lexicographical>, &'static [u8]>;
+
+const MAGIC: &[u8; 8] = b"REDBBNCD";
+const VERSION: u8 = 1;
+
+const FRAME_TABLE: u8 = 1;
+const FRAME_ENTRY: u8 = 2;
+const FRAME_END: u8 = 3;
+
+/// Prefix of the names of tables used internally, e.g. for the changelog
+const INTERNAL_PREFIX: &str = "redb_bincode::";
+
+/// A table or multimap table definition, regardless of its types, see
+/// [`Database::export_tables`]
+pub trait AnyTableDefinition {
+    fn name(&self) -> &str;
+
+    fn is_multimap(&self) -> bool;
+
+    /// Whether the keys are sorted [`Lexicographical`]
+    fn is_lexicographical(&self) -> bool;
+}
+
+impl<K, V, S: 'static, KC, VC> AnyTableDefinition for TableDefinition<'_, K, V, S, KC, VC> {
+    fn name(&self) -> &str {
+        TableDefinition::name(self)
+    }
+
+    fn is_multimap(&self) -> bool {
+        false
+    }
+
+    fn is_lexicographical(&self) -> bool {
+        TypeId::of::<S>() == TypeId::of::<Lexicographical>()
+    }
+}
+
+impl<K, V, S: 'static, KC, VC> AnyTableDefinition for MultimapTableDefinition<'_, K, V, S, KC, VC> {
+    fn name(&self) -> &str {
+        MultimapTableDefinition::name(self)
+    }
+
+    fn is_multimap(&self) -> bool {
+        true
+    }
+
+    fn is_lexicographical(&self) -> bool {
+        TypeId::of::<S>() == TypeId::of::<Lexicographical>()
+    }
+}
+
+/// Table name and kind, as found in the database
+struct TableMeta {
+    name: String,
+    multimap: bool,
+}
+
+impl Database {
+    /// Write all tables to `writer`, from a single read transaction
+    ///
+    /// The format is independent of the `redb` file format: a header, then
+    /// for every table a frame with its name and kind followed by frames with
+    /// its raw (encoded and possibly encrypted) entries, and finally a frame
+    /// with the number of entries and a CRC-32 checksum of everything before
+    /// the checksum. Only tables created through this crate can be exported.
+    ///
+    /// Internal tables, like the changelog's, are skipped. Tables are
+    /// imported sorted [`Lexicographical`], so exporting a table whose keys
+    /// are not stored in that order (i.e. with a custom [`SortOrder`](crate::SortOrder))
+    /// fails with [`Error::UnsupportedSortOrder`].
+    pub fn export(&self, writer: impl io::Write) -> Result<(), Error> {
+        self.export_inner(writer, None)
+    }
+
+    /// Like [`Self::export`], but only the given tables
+    ///
+    /// Tables that don't exist are exported as empty. Definitions with a
+    /// custom [`SortOrder`](crate::SortOrder) fail with
+    /// [`Error::UnsupportedSortOrder`] right away.
+    pub fn export_tables(
+        &self,
+        writer: impl io::Write,
+        tables: &[&dyn AnyTableDefinition],
+    ) -> Result<(), Error> {
+        if let Some(table) = tables.iter().find(|table| !table.is_lexicographical()) {
+            return Err(Error::UnsupportedSortOrder(table.name().to_owned()));
+        }
+        let tables = tables
+            .iter()
+            .map(|table| TableMeta {
+                name: table.name().to_owned(),
+                multimap: table.is_multimap(),
+            })
+            .collect();
+        self.export_inner(writer, Some(tables))
+    }
+
+    fn export_inner(
+        &self,
+        writer: impl io::Write,
+        tables: Option<Vec<TableMeta>>,
+    ) -> Result<(), Error> {
+        let tx = self.begin_read()?;
+        let tx = tx.as_raw();
+        let tables = match tables {
+            Some(tables) => tables,
+            None => tx
+                .list_tables()?
+                .map(|handle| TableMeta {
+                    name: handle.name().to_owned(),
+                    multimap: false,
+                })
+                .chain(tx.list_multimap_tables()?.map(|handle| TableMeta {
+                    name: handle.name().to_owned(),
+                    multimap: true,
+                }))
+                .filter(|table| !table.name.starts_with(INTERNAL_PREFIX))
+                .collect(),
+        };
+
+        let mut writer = ChecksumWriter::new(writer);
+        writer.write(MAGIC)?;
+        writer.write(&[VERSION])?;
+        let mut entries = 0u64;
+        for table in &tables {
+            writer.write(&[FRAME_TABLE, u8::from(table.multimap)])?;
+            writer.write_bytes(table.name.as_bytes())?;
+
+            if table.multimap {
+                let raw = match tx.open_multimap_table(RawMultimapTableDefinition::new(&table.name))
+                {
+                    Ok(raw) => raw,
+                    Err(TableError::TableDoesNotExist(_)) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                let mut order = OrderCheck::new(&table.name);
+                for entry in raw.iter()? {
+                    let (key, values) = entry?;
+                    order.check(key.value())?;
+                    for value in values {
+                        writer.write_entry(key.value(), value?.value())?;
+                        entries += 1;
+                    }
+                }
+            } else {
+                let raw = match tx.open_table(RawTableDefinition::new(&table.name)) {
+                    Ok(raw) => raw,
+                    Err(TableError::TableDoesNotExist(_)) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                let mut order = OrderCheck::new(&table.name);
+                for entry in raw.iter()? {
+                    let (key, value) = entry?;
+                    order.check(key.value())?;
+                    writer.write_entry(key.value(), value.value())?;
+                    entries += 1;
+                }
+            }
+        }
+
+        writer.write(&[FRAME_END])?;
+        writer.write(&entries.to_be_bytes())?;
+        let checksum = writer.crc.finish();
+        writer.write(&checksum.to_be_bytes())?;
+        writer.inner.flush()?;
+        Ok(())
+    }
+
+    /// Import tables written by [`Self::export`] in a single write
+    /// transaction
+    ///
+    /// Entries are inserted into the tables as they are, creating missing
+    /// tables with the [`Lexicographical`] sort order. Existing tables are
+    /// merged into, not replaced: keys in the stream overwrite existing
+    /// ones, other keys are kept, and values are added to multimap tables.
+    /// Existing tables with a custom [`SortOrder`](crate::SortOrder) must not
+    /// be imported into.
+    ///
+    /// Nothing is committed unless the whole stream is read and its checksum
+    /// matches, otherwise [`Error::CorruptExport`] is returned. Imports can't
+    /// be recorded in the changelog, so they fail with [`Error::NotLogged`]
+    /// if the database keeps one.
+    pub fn import(&self, reader: impl io::Read) -> Result<(), Error> {
+        let mut reader = ChecksumReader::new(reader);
+        let mut header = [0; MAGIC.len() + 1];
+        reader.read(&mut header)?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(Error::CorruptExport("not an export"));
+        }
+        if header[MAGIC.len()] != VERSION {
+            return Err(Error::CorruptExport("unsupported version"));
+        }
+
+        let tx = self.begin_write()?;
+        tx.check_logged(|| "importing".to_owned())?;
+        let res = (|| {
+            let raw_tx = tx.as_raw();
+            let mut table = None;
+            let mut entries = 0u64;
+            loop {
+                let mut tag = [0];
+                reader.read(&mut tag)?;
+                match tag[0] {
+                    FRAME_TABLE => {
+                        let mut multimap = [0];
+                        reader.read(&mut multimap)?;
+                        let name = String::from_utf8(reader.read_bytes()?)
+                            .map_err(|_| Error::CorruptExport("invalid table name"))?;
+                        // close the previous table first
+                        drop(table.take());
+                        table = Some(match multimap[0] {
+                            0 => ImportTable::Table(
+                                raw_tx.open_table(RawTableDefinition::new(&name))?,
+                            ),
+                            1 => ImportTable::Multimap(
+                                raw_tx
+                                    .open_multimap_table(RawMultimapTableDefinition::new(&name))?,
+                            ),
+                            _ => return Err(Error::CorruptExport("invalid table kind")),
+                        });
+                    }
+                    FRAME_ENTRY => {
+                        let key = reader.read_bytes()?;
+                        let value = reader.read_bytes()?;
+                        match &mut table {
+                            Some(ImportTable::Table(table)) => {
+                                table.insert(&key[..], &value[..])?;
+                            }
+                            Some(ImportTable::Multimap(table)) => {
+                                table.insert(&key[..], &value[..])?;
+                            }
+                            None => return Err(Error::CorruptExport("entry outside of a table")),
+                        }
+                        entries += 1;
+                    }
+                    FRAME_END => {
+                        let mut expected_entries = [0; 8];
+                        reader.read(&mut expected_entries)?;
+                        let checksum = reader.crc.finish();
+                        let mut expected_checksum = [0; 4];
+                        reader.read(&mut expected_checksum)?;
+                        if u64::from_be_bytes(expected_entries) != entries {
+                            return Err(Error::CorruptExport("entry count mismatch"));
+                        }
+                        if u32::from_be_bytes(expected_checksum) != checksum {
+                            return Err(Error::CorruptExport("checksum mismatch"));
+                        }
+                        return Ok(());
+                    }
+                    _ => return Err(Error::CorruptExport("invalid frame")),
+                }
+            }
+        })();
+
+        match res {
+            Ok(()) => Ok(tx.commit()?),
+            Err(e) => {
+                let _ = tx.abort();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Fails on keys not in [`Lexicographical`] order, which can't be imported
+struct OrderCheck<'a> {
+    table: &'a str,
+    prev: Option<Vec<u8>>,
+}
+
+impl<'a> OrderCheck<'a> {
+    fn new(table: &'a str) -> Self {
+        Self { table, prev: None }
+    }
+
+    fn check(&mut self, key: &[u8]) -> Result<(), Error> {
+        if self.prev.as_deref().is_some_and(|prev| key < prev) {
+            return Err(Error::UnsupportedSortOrder(self.table.to_owned()));
+        }
+        let prev = self.prev.get_or_insert_with(Vec::new);
+        prev.clear();
+        prev.extend_from_slice(key);
+        Ok(())
+    }
+}
+
+enum ImportTable<'txn> {
+    Table(redb::Table<'txn, SortKey<Lexicographical>, &'static [u8]>),
+    Multimap(redb::MultimapTable<'txn, SortKey<Lexicographical>, &'static [u8]>),
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: io::Write> ChecksumWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            crc: Crc32::default(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.crc.update(bytes);
+        self.inner.write_all(bytes)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record too large"))?;
+        self.write(&len.to_be_bytes())?;
+        Ok(self.write(bytes)?)
+    }
+
+    fn write_entry(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.write(&[FRAME_ENTRY])?;
+        self.write_bytes(key)?;
+        self.write_bytes(value)
+    }
+}
+
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: io::Read> ChecksumReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            crc: Crc32::default(),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.inner.read_exact(buf).map_err(truncated)?;
+        self.crc.update(buf);
+        Ok(())
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let mut len = [0; 4];
+        self.read(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        // don't trust `len` for allocating
+        let mut buf = vec![];
+        (&mut self.inner).take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(Error::CorruptExport("truncated"));
+        }
+        self.crc.update(&buf);
+        Ok(buf)
+    }
+}
+
+fn truncated(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => Error::CorruptExport("truncated"),
+        _ => Error::Io(e),
+    }
+}
+
+/// CRC-32 (IEEE)
+struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self(!0)
+    }
+}
+
+impl Crc32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = Self::TABLE[((self.0 ^ u32::from(b)) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
@@ -0,0 +1,431 @@
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::{fmt, ops};
+
+use redb::ReadableMultimapTable as _;
+
+use crate::{
+    encode_bound, AccessGuard, Bincode, Codec, CodecEncode, Error, Lexicographical,
+    MultimapTableDefinition, ReadOnlyTable, SortKey, SortOrder, Table, TableCipher,
+};
+
+type RawIndexTable<'txn> = redb::MultimapTable<'txn, SortKey<Lexicographical>, &'static [u8]>;
+type RawReadOnlyIndexTable = redb::ReadOnlyMultimapTable<SortKey<Lexicographical>, &'static [u8]>;
+
+/// Definition of a secondary index over the values of a table
+///
+/// The index is stored in a multimap table named `name`, mapping the index
+/// key returned by `extract` (encoded with `IKC`) to the primary keys
+/// (encoded with `KC`, which must match the primary table).
+///
+/// ```
+/// use redb_bincode::{IndexDefinition, TableDefinition};
+///
+/// #[derive(bincode::Encode, bincode::Decode)]
+/// struct User {
+///     email: String,
+///     age: u32,
+/// }
+///
+/// const USERS: TableDefinition<u64, User> = TableDefinition::new("users");
+/// const USERS_BY_EMAIL: IndexDefinition<u64, User, String> =
+///     IndexDefinition::new("users_by_email", |user: &User| user.email.clone()).unique();
+/// const USERS_BY_AGE: IndexDefinition<u64, User, u32> =
+///     IndexDefinition::new("users_by_age", |user: &User| user.age);
+/// ```
+///
+/// Index keys are stored unencrypted, even for
+/// [encrypted](crate::TableDefinition::encrypted) tables.
+pub struct IndexDefinition<'a, K, V, IK, KC = Bincode, IKC = Bincode> {
+    name: &'a str,
+    unique: bool,
+    extract: fn(&V) -> IK,
+    _key_type: PhantomData<K>,
+    _key_codec: PhantomData<KC>,
+    _index_key_codec: PhantomData<IKC>,
+}
+
+impl<'a, K, V, IK, KC, IKC> IndexDefinition<'a, K, V, IK, KC, IKC> {
+    pub const fn new(name: &'a str, extract: fn(&V) -> IK) -> Self {
+        Self {
+            name,
+            unique: false,
+            extract,
+            _key_type: PhantomData,
+            _key_codec: PhantomData,
+            _index_key_codec: PhantomData,
+        }
+    }
+
+    /// Reject values with an index key already used by a different primary
+    /// key, see [`Error::UniqueIndex`]
+    pub const fn unique(self) -> Self {
+        Self {
+            unique: true,
+            ..self
+        }
+    }
+
+    pub const fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The underlying multimap table, from index keys to primary keys
+    pub const fn table(&self) -> MultimapTableDefinition<'a, IK, K, Lexicographical, IKC, KC> {
+        MultimapTableDefinition::new(self.name)
+    }
+}
+
+/// An index that can be maintained for a table with values `V`
+///
+/// Implemented by [`IndexDefinition`], to allow indexes with different key
+/// types to be passed together to
+/// [`WriteTransaction::open_indexed_table`](crate::WriteTransaction::open_indexed_table).
+pub trait SecondaryIndex<V> {
+    fn name(&self) -> &str;
+
+    fn is_unique(&self) -> bool;
+
+    /// Encode the index key of `value` into `buf`
+    fn encode_index_key(&self, value: &V, buf: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+impl<'a, K, V, IK, KC, IKC> SecondaryIndex<V> for IndexDefinition<'a, K, V, IK, KC, IKC>
+where
+    IKC: CodecEncode<IK>,
+{
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    fn encode_index_key(&self, value: &V, buf: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(IKC::encode_into(&(self.extract)(value), buf)?)
+    }
+}
+
+/// A [`Table`] that keeps its secondary indexes in sync
+///
+/// Opened with
+/// [`WriteTransaction::open_indexed_table`](crate::WriteTransaction::open_indexed_table).
+/// Only modifications made through this type update the indexes.
+pub struct IndexedTable<'txn, 'i, K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    table: Table<'txn, K, V, S, KC, VC>,
+    indexes: Vec<(&'i dyn SecondaryIndex<V>, RawIndexTable<'txn>)>,
+}
+
+impl<'txn, 'i, K, V, S, KC, VC> IndexedTable<'txn, 'i, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub(crate) fn new(
+        table: Table<'txn, K, V, S, KC, VC>,
+        indexes: Vec<(&'i dyn SecondaryIndex<V>, RawIndexTable<'txn>)>,
+    ) -> Self {
+        Self { table, indexes }
+    }
+
+    /// The primary table, for reading
+    pub fn as_table(&self) -> &Table<'txn, K, V, S, KC, VC> {
+        &self.table
+    }
+
+    /// Insert `value` under `key`, updating all indexes, and return the
+    /// previous value
+    ///
+    /// Fails with [`Error::UniqueIndex`] without modifying anything if a
+    /// unique index key is already used by a different key.
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<V>, Error> {
+        let mut primary_key = vec![];
+        KC::encode_into(key, &mut primary_key)?;
+
+        let index_keys = encode_index_keys(&self.indexes, value)?;
+
+        for ((index, index_table), index_key) in self.indexes.iter().zip(&index_keys) {
+            if !index.is_unique() {
+                continue;
+            }
+            for existing in index_table.get(&index_key[..])? {
+                if existing?.value() != primary_key {
+                    return Err(Error::UniqueIndex(index.name().to_owned()));
+                }
+            }
+        }
+
+        // decode before writing anything, so a failure changes nothing
+        let previous = self
+            .table
+            .try_get(key)?
+            .map(|v| v.value_try())
+            .transpose()?;
+        self.table.try_insert(key, value)?;
+        if let Some(previous) = &previous {
+            self.remove_index_entries(&primary_key, previous)?;
+        }
+        for ((_, index_table), index_key) in self.indexes.iter_mut().zip(&index_keys) {
+            index_table.insert(&index_key[..], &primary_key[..])?;
+        }
+        Ok(previous)
+    }
+
+    /// Remove `key`, updating all indexes, and return its value
+    ///
+    /// Fails without removing anything if the value fails to decode, as its
+    /// index entries can't be found.
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, Error> {
+        let previous = self
+            .table
+            .try_get(key)?
+            .map(|v| v.value_try())
+            .transpose()?;
+        if previous.is_some() {
+            self.table.try_remove(key)?;
+        }
+        if let Some(previous) = &previous {
+            let mut primary_key = vec![];
+            KC::encode_into(key, &mut primary_key)?;
+            self.remove_index_entries(&primary_key, previous)?;
+        }
+        Ok(previous)
+    }
+
+    /// Keep only the entries for which `predicate` returns `true`, updating
+    /// all indexes
+    ///
+    /// Like [`Table::try_retain`], stops at the first entry that fails to
+    /// decode and returns the error, keeping that and all remaining entries.
+    pub fn retain<F>(&mut self, mut predicate: F) -> Result<(), Error>
+    where
+        F: for<'f> FnMut(&'f K, &'f V) -> bool,
+    {
+        let indexes = &self.indexes;
+        let mut removed = vec![];
+        let mut encode_res = Ok(());
+        let res = self.table.try_retain(|k, v| {
+            if encode_res.is_err() || predicate(k, v) {
+                return true;
+            }
+            let mut primary_key = vec![];
+            match KC::encode_into(k, &mut primary_key)
+                .map_err(Error::from)
+                .and_then(|()| encode_index_keys(indexes, v))
+            {
+                Ok(index_keys) => {
+                    removed.push((primary_key, index_keys));
+                    false
+                }
+                Err(e) => {
+                    encode_res = Err(e);
+                    true
+                }
+            }
+        });
+        for (primary_key, index_keys) in removed {
+            for ((_, index_table), index_key) in self.indexes.iter_mut().zip(&index_keys) {
+                index_table.remove(&index_key[..], &primary_key[..])?;
+            }
+        }
+        res.and(encode_res)
+    }
+
+    /// All records with index key `index_key` in `index`, in the order of
+    /// their primary keys
+    pub fn get_by_index<IK, IKC, Q>(
+        &self,
+        index: &IndexDefinition<'_, K, V, IK, KC, IKC>,
+        index_key: &Q,
+    ) -> Result<Vec<(K, V)>, Error>
+    where
+        IK: Borrow<Q>,
+        IKC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.range_by_index(
+            index,
+            (
+                ops::Bound::Included(index_key),
+                ops::Bound::Included(index_key),
+            ),
+        )
+    }
+
+    /// All records with index keys within `range` in `index`, in the order of
+    /// their index keys
+    ///
+    /// Fails with [`Error::IndexNotOpened`] if `index` was not passed when
+    /// opening the table.
+    pub fn range_by_index<'a, IK, IKC, Q>(
+        &self,
+        index: &IndexDefinition<'_, K, V, IK, KC, IKC>,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Vec<(K, V)>, Error>
+    where
+        IK: Borrow<Q>,
+        IKC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        let index_table = self
+            .indexes
+            .iter()
+            .find(|(i, _)| i.name() == index.name)
+            .map(|(_, table)| table)
+            .ok_or_else(|| Error::IndexNotOpened(index.name.to_owned()))?;
+        lookup::<K, V, S, KC, VC, IKC, Q>(
+            &self.table.inner,
+            self.table.cipher.as_deref(),
+            index_table,
+            range,
+        )
+    }
+
+    fn remove_index_entries(&mut self, primary_key: &[u8], value: &V) -> Result<(), Error> {
+        let mut index_key = vec![];
+        for (index, index_table) in &mut self.indexes {
+            index_key.clear();
+            index.encode_index_key(value, &mut index_key)?;
+            index_table.remove(&index_key[..], primary_key)?;
+        }
+        Ok(())
+    }
+}
+
+/// The keys of `value` in all `indexes`
+fn encode_index_keys<V>(
+    indexes: &[(&dyn SecondaryIndex<V>, RawIndexTable<'_>)],
+    value: &V,
+) -> Result<Vec<Vec<u8>>, Error> {
+    indexes
+        .iter()
+        .map(|(index, _)| {
+            let mut buf = vec![];
+            index.encode_index_key(value, &mut buf)?;
+            Ok(buf)
+        })
+        .collect()
+}
+
+/// Read-only counterpart of [`IndexedTable`], for lookups
+///
+/// Opened with
+/// [`ReadTransaction::open_indexed_table`](crate::ReadTransaction::open_indexed_table).
+pub struct ReadOnlyIndexedTable<K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    table: ReadOnlyTable<K, V, S, KC, VC>,
+    indexes: Vec<(String, RawReadOnlyIndexTable)>,
+}
+
+impl<K, V, S, KC, VC> ReadOnlyIndexedTable<K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub(crate) fn new(
+        table: ReadOnlyTable<K, V, S, KC, VC>,
+        indexes: Vec<(String, RawReadOnlyIndexTable)>,
+    ) -> Self {
+        Self { table, indexes }
+    }
+
+    /// The primary table
+    pub fn as_table(&self) -> &ReadOnlyTable<K, V, S, KC, VC> {
+        &self.table
+    }
+
+    /// See [`IndexedTable::get_by_index`]
+    pub fn get_by_index<IK, IKC, Q>(
+        &self,
+        index: &IndexDefinition<'_, K, V, IK, KC, IKC>,
+        index_key: &Q,
+    ) -> Result<Vec<(K, V)>, Error>
+    where
+        IK: Borrow<Q>,
+        IKC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.range_by_index(
+            index,
+            (
+                ops::Bound::Included(index_key),
+                ops::Bound::Included(index_key),
+            ),
+        )
+    }
+
+    /// See [`IndexedTable::range_by_index`]
+    pub fn range_by_index<'a, IK, IKC, Q>(
+        &self,
+        index: &IndexDefinition<'_, K, V, IK, KC, IKC>,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Vec<(K, V)>, Error>
+    where
+        IK: Borrow<Q>,
+        IKC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        let index_table = self
+            .indexes
+            .iter()
+            .find(|(name, _)| name == index.name)
+            .map(|(_, table)| table)
+            .ok_or_else(|| Error::IndexNotOpened(index.name.to_owned()))?;
+        lookup::<K, V, S, KC, VC, IKC, Q>(
+            &self.table.inner,
+            self.table.cipher.as_deref(),
+            index_table,
+            range,
+        )
+    }
+}
+
+/// Resolve the primary keys of the index entries within `range` to records
+///
+/// Entries pointing at missing primary keys are skipped.
+fn lookup<'a, K, V, S, KC, VC, IKC, Q>(
+    primary: &impl redb::ReadableTable<SortKey<S>, &'static [u8]>,
+    cipher: Option<&TableCipher>,
+    index: &impl redb::ReadableMultimapTable<SortKey<Lexicographical>, &'static [u8]>,
+    range: impl ops::RangeBounds<Q> + 'a,
+) -> Result<Vec<(K, V)>, Error>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+    IKC: CodecEncode<Q>,
+    Q: ?Sized,
+{
+    let mut start_bound_buf = vec![];
+    let mut end_bound_buf = vec![];
+    let start_bound = encode_bound::<IKC, _>(range.start_bound(), &mut start_bound_buf)?
+        .map(|()| SortKey(&start_bound_buf[..]));
+    let end_bound = encode_bound::<IKC, _>(range.end_bound(), &mut end_bound_buf)?
+        .map(|()| SortKey(&end_bound_buf[..]));
+
+    let mut records = vec![];
+    for entry in index.range((start_bound, end_bound))? {
+        let (_, primary_keys) = entry?;
+        for primary_key in primary_keys {
+            let primary_key = primary_key?;
+            let Some(value) = primary.get(primary_key.value())? else {
+                continue;
+            };
+            records.push((
+                KC::decode(primary_key.value())?,
+                AccessGuard::<V, &'static [u8], VC>::from(value)
+                    .with_cipher(cipher, primary_key.value())
+                    .value_try()?,
+            ));
+        }
+    }
+    Ok(records)
+}
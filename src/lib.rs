@@ -1,25 +1,58 @@
 #![doc = include_str!("../README.md")]
 
 mod access_guard;
+#[cfg(feature = "tokio")]
+mod async_database;
+mod bound;
+mod changelog;
+mod changes;
+mod codec;
+mod compression;
 mod database;
+mod encryption;
+mod error;
+mod export;
+mod index;
+pub mod memcomparable;
+mod multimap_table;
+mod page;
+mod prefix;
 mod range;
 mod readable_table;
 mod sort;
 mod tx;
+mod versioned;
 
 use std::borrow::Borrow;
 use std::cell::UnsafeCell;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::{fmt, ops};
 
 pub use access_guard::*;
+#[cfg(feature = "tokio")]
+pub use async_database::*;
+pub use bincode::error::{DecodeError, EncodeError};
+pub use bound::*;
+pub use changelog::ChangelogEntry;
+pub use changes::{Change, ListenerId};
+pub use codec::*;
+pub use compression::*;
 pub use database::*;
+pub use encryption::*;
+pub use error::*;
+pub use export::AnyTableDefinition;
+pub use index::*;
+pub use multimap_table::*;
+pub use page::{Cursor, Direction, Page};
+pub use prefix::*;
 pub use range::*;
 pub use readable_table::*;
-use redb::ReadableTable as _;
-pub use redb::StorageError;
+pub use redb::{Durability, StorageError};
+use redb::{ReadableTable as _, ReadableTableMetadata as _};
 pub use sort::*;
 pub use tx::*;
+pub use versioned::*;
 
 pub const BINCODE_CONFIG: bincode::config::Configuration<bincode::config::BigEndian> =
     bincode::config::standard()
@@ -59,280 +92,711 @@ unsafe fn with_encode_value_buf<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
         res
     })
 }
+/// Encode a range bound into `buf`, keeping only its shape
+fn encode_bound<C, Q>(bound: ops::Bound<&Q>, buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error>
+where
+    C: CodecEncode<Q>,
+    Q: ?Sized,
+{
+    Ok(match bound {
+        ops::Bound::Included(q) => {
+            C::encode_into(q, buf)?;
+            ops::Bound::Included(())
+        }
+        ops::Bound::Excluded(q) => {
+            C::encode_into(q, buf)?;
+            ops::Bound::Excluded(())
+        }
+        ops::Bound::Unbounded => ops::Bound::Unbounded,
+    })
+}
 
-pub struct ReadOnlyTable<K, V, S = Lexicographical>
+/// Decode a raw key-value pair, for use in `redb` predicates
+fn decode_pair<K, V, KC, VC>(
+    cipher: Option<&TableCipher>,
+    raw_key: &[u8],
+    raw_val: &[u8],
+) -> Result<(K, V), Error>
+where
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
+{
+    let raw_val = encryption::decrypt_value(cipher, raw_key, raw_val)?;
+    Ok((KC::decode(raw_key)?, VC::decode(&raw_val)?))
+}
+
+/// Record the removal of an entry `retain` won't keep
+fn retain_recorded(
+    changes: Option<&changes::ChangeRecorder<'_>>,
+    raw_key: &[u8],
+    raw_val: &[u8],
+    keep: bool,
+) -> bool {
+    if let (Some(changes), false) = (changes, keep) {
+        changes.record(raw_key, Some(raw_val), None);
+    }
+    keep
+}
+
+pub struct ReadOnlyTable<K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
 where
     S: SortOrder + fmt::Debug + 'static,
 {
     inner: redb::ReadOnlyTable<sort::SortKey<S>, &'static [u8]>,
+    cipher: Option<Arc<TableCipher>>,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
 }
 
-impl<K, V, S> ReadOnlyTable<K, V, S>
+impl<K, V, S, KC, VC> ReadOnlyTable<K, V, S, KC, VC>
 where
     S: SortOrder + fmt::Debug + 'static,
-    K: bincode::Encode + bincode::Decode,
-    V: bincode::Encode + bincode::Decode,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
     pub fn as_raw(&self) -> &redb::ReadOnlyTable<sort::SortKey<S>, &'static [u8]> {
         &self.inner
     }
 
+    pub fn len(&self) -> Result<u64, StorageError> {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> Result<bool, StorageError> {
+        self.inner.is_empty()
+    }
+
     #[allow(clippy::type_complexity)]
     pub fn first(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
-        Ok(self
-            .inner
-            .first()?
-            .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))))
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
+        Ok(self.inner.first()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher.as_deref(), k.value());
+            (AccessGuard::from(k), v)
+        }))
     }
 
     #[allow(clippy::type_complexity)]
     pub fn last(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
-        Ok(self
-            .inner
-            .last()?
-            .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))))
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
+        Ok(self.inner.last()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher.as_deref(), k.value());
+            (AccessGuard::from(k), v)
+        }))
     }
 
     pub fn range<'a, Q>(
         &self,
         range: impl ops::RangeBounds<Q> + 'a,
-    ) -> Result<Range<'_, K, V, SortKey<S>>, StorageError>
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
+        self.try_range(range).map_err(Error::expect_storage)
+    }
+
+    pub fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        let redb_range = unsafe {
+            with_encode_key_buf(|start_bound_buf| {
+                let start_bound = encode_bound::<KC, _>(range.start_bound(), start_bound_buf)?;
+
+                with_encode_value_buf(|end_bound_buf| {
+                    let end_bound = encode_bound::<KC, _>(range.end_bound(), end_bound_buf)?;
+
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(self.inner.range((start_bound, end_bound))?)
+                })
+            })?
+        };
+        Ok(Range::from(redb_range).with_cipher(self.cipher.as_deref()))
+    }
+
+    /// Like [`Self::range`], but with the start and end of the range given
+    /// separately, possibly as different types
+    ///
+    /// See [`KeyBound`] for the supported bounds.
+    pub fn range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError> {
+        self.try_range_between(start, end)
+            .map_err(Error::expect_storage)
+    }
+
+    pub fn try_range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error> {
         let redb_range = unsafe {
             with_encode_key_buf(|start_bound_buf| {
-                let start_bound_size = range.start_bound().map(|bound| {
-                    bincode::encode_into_std_write(bound, start_bound_buf, BINCODE_CONFIG)
-                        .expect("encoding can't fail")
-                });
+                let start_bound = start.encode_start(start_bound_buf)?;
 
                 with_encode_value_buf(|end_bound_buf| {
-                    let end_bound_size = range.end_bound().map(|bound| {
-                        bincode::encode_into_std_write(bound, end_bound_buf, BINCODE_CONFIG)
-                            .expect("encoding can't fail")
-                    });
-
-                    let start_bound =
-                        start_bound_size.map(|size| SortKey(&start_bound_buf[..size]));
-                    let end_bound = end_bound_size.map(|size| SortKey(&end_bound_buf[..size]));
-                    self.inner.range((start_bound, end_bound))
+                    let end_bound = end.encode_end(end_bound_buf)?;
+
+                    let Some(start_bound) = start_bound else {
+                        let empty = SortKey(&[][..])..SortKey(&[][..]);
+                        return Ok::<_, Error>(self.inner.range(empty)?);
+                    };
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(self.inner.range((start_bound, end_bound))?)
                 })
             })?
         };
-        Ok(Range::from(redb_range))
+        Ok(Range::from(redb_range).with_cipher(self.cipher.as_deref()))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get<Q>(
+        &self,
+        key: &Q,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_get(key).map_err(Error::expect_storage)
     }
 
-    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    #[allow(clippy::type_complexity)]
+    pub fn try_get<Q>(
+        &self,
+        key: &Q,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         unsafe {
-            Ok(with_encode_key_buf(|buf| {
-                let size = bincode::encode_into_std_write(key, buf, BINCODE_CONFIG)
-                    .expect("encoding can't fail");
-                self.inner.get(&buf[..size])
-            })?
-            .map(AccessGuard::from))
+            with_encode_key_buf(|buf| {
+                KC::encode_into(key, buf)?;
+                Ok::<_, Error>(
+                    self.inner
+                        .get(&buf[..])?
+                        .map(|v| AccessGuard::from(v).with_cipher(self.cipher.as_deref(), buf)),
+                )
+            })
         }
     }
 }
 
-pub struct Table<'txn, K, V, S = Lexicographical>
+pub struct Table<'txn, K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
 where
     S: SortOrder + fmt::Debug + 'static,
 {
     inner: redb::Table<'txn, sort::SortKey<S>, &'static [u8]>,
+    cipher: Option<Arc<TableCipher>>,
+    changes: Option<changes::ChangeRecorder<'txn>>,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
 }
 
-impl<'txn, K, V, S> Table<'txn, K, V, S>
+impl<'txn, K, V, S, KC, VC> Table<'txn, K, V, S, KC, VC>
 where
     S: SortOrder + fmt::Debug + 'static,
-    K: bincode::Encode + bincode::Decode,
-    V: bincode::Encode + bincode::Decode,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
-    pub fn as_raw(&self) -> &redb::Table<sort::SortKey<S>, &'static [u8]> {
+    pub fn as_raw(&self) -> &redb::Table<'_, sort::SortKey<S>, &'static [u8]> {
         &self.inner
     }
-    pub fn as_raw_mut(&mut self) -> &'txn mut redb::Table<'_, sort::SortKey<S>, &'static [u8]> {
+    pub fn as_raw_mut(&mut self) -> &mut redb::Table<'txn, sort::SortKey<S>, &'static [u8]> {
         &mut self.inner
     }
 
+    pub fn len(&self) -> Result<u64, StorageError> {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> Result<bool, StorageError> {
+        self.inner.is_empty()
+    }
+
     #[allow(clippy::type_complexity)]
     pub fn first(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
-        Ok(self
-            .inner
-            .first()?
-            .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))))
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
+        Ok(self.inner.first()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher.as_deref(), k.value());
+            (AccessGuard::from(k), v)
+        }))
     }
 
     #[allow(clippy::type_complexity)]
     pub fn last(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
-        Ok(self
-            .inner
-            .last()?
-            .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))))
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
+        Ok(self.inner.last()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher.as_deref(), k.value());
+            (AccessGuard::from(k), v)
+        }))
     }
 
     pub fn range<'a, Q>(
         &self,
         range: impl ops::RangeBounds<Q> + 'a,
-    ) -> Result<Range<'_, K, V, SortKey<S>>, StorageError>
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_range(range).map_err(Error::expect_storage)
+    }
+
+    pub fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         let redb_range = unsafe {
             with_encode_key_buf(|start_bound_buf| {
-                let start_bound_size = range.start_bound().map(|bound| {
-                    bincode::encode_into_std_write(bound, start_bound_buf, BINCODE_CONFIG)
-                        .expect("encoding can't fail")
-                });
+                let start_bound = encode_bound::<KC, _>(range.start_bound(), start_bound_buf)?;
 
                 with_encode_value_buf(|end_bound_buf| {
-                    let end_bound_size = range.end_bound().map(|bound| {
-                        bincode::encode_into_std_write(bound, end_bound_buf, BINCODE_CONFIG)
-                            .expect("encoding can't fail")
-                    });
-
-                    let start_bound =
-                        start_bound_size.map(|size| SortKey(&start_bound_buf[..size]));
-                    let end_bound = end_bound_size.map(|size| SortKey(&end_bound_buf[..size]));
-                    self.inner.range((start_bound, end_bound))
+                    let end_bound = encode_bound::<KC, _>(range.end_bound(), end_bound_buf)?;
+
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(self.inner.range((start_bound, end_bound))?)
                 })
             })?
         };
-        Ok(Range::from(redb_range))
+        Ok(Range::from(redb_range).with_cipher(self.cipher.as_deref()))
+    }
+
+    /// Like [`Self::range`], but with the start and end of the range given
+    /// separately, possibly as different types
+    ///
+    /// See [`KeyBound`] for the supported bounds.
+    pub fn range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError> {
+        self.try_range_between(start, end)
+            .map_err(Error::expect_storage)
+    }
+
+    pub fn try_range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error> {
+        let redb_range = unsafe {
+            with_encode_key_buf(|start_bound_buf| {
+                let start_bound = start.encode_start(start_bound_buf)?;
+
+                with_encode_value_buf(|end_bound_buf| {
+                    let end_bound = end.encode_end(end_bound_buf)?;
+
+                    let Some(start_bound) = start_bound else {
+                        let empty = SortKey(&[][..])..SortKey(&[][..]);
+                        return Ok::<_, Error>(self.inner.range(empty)?);
+                    };
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(self.inner.range((start_bound, end_bound))?)
+                })
+            })?
+        };
+        Ok(Range::from(redb_range).with_cipher(self.cipher.as_deref()))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get<Q>(
+        &self,
+        key: &Q,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_get(key).map_err(Error::expect_storage)
     }
 
-    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    #[allow(clippy::type_complexity)]
+    pub fn try_get<Q>(
+        &self,
+        key: &Q,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         unsafe {
-            Ok(with_encode_key_buf(|buf| {
-                let size = bincode::encode_into_std_write(key, buf, BINCODE_CONFIG)
-                    .expect("encoding can't fail");
-                self.inner.get(&buf[..size])
-            })?
-            .map(AccessGuard::from))
+            with_encode_key_buf(|buf| {
+                KC::encode_into(key, buf)?;
+                Ok::<_, Error>(
+                    self.inner
+                        .get(&buf[..])?
+                        .map(|v| AccessGuard::from(v).with_cipher(self.cipher.as_deref(), buf)),
+                )
+            })
         }
     }
 
+    #[allow(clippy::type_complexity)]
     pub fn insert<KQ, VQ>(
         &mut self,
         key: &KQ,
         value: &VQ,
-    ) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
     where
         K: Borrow<KQ>,
         V: Borrow<VQ>,
-        KQ: bincode::Encode + ?Sized,
-        VQ: bincode::Encode + ?Sized,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+        VC: CodecEncode<VQ>,
+        VQ: ?Sized,
     {
-        Ok(unsafe {
+        self.try_insert(key, value).map_err(Error::expect_storage)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn try_insert<KQ, VQ>(
+        &mut self,
+        key: &KQ,
+        value: &VQ,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+        VC: CodecEncode<VQ>,
+        VQ: ?Sized,
+    {
+        unsafe {
             with_encode_key_buf(|key_buf| {
-                let key_size = bincode::encode_into_std_write(key, key_buf, BINCODE_CONFIG)
-                    .expect("encoding can't fail");
+                KC::encode_into(key, key_buf)?;
 
                 with_encode_value_buf(|value_buf| {
-                    let value_size =
-                        bincode::encode_into_std_write(value, value_buf, BINCODE_CONFIG)
-                            .expect("encoding can't fail");
-
-                    self.inner
-                        .insert(&key_buf[..key_size], &value_buf[..value_size])
+                    VC::encode_into(value, value_buf)?;
+                    encryption::encrypt_value_buf(self.cipher.as_deref(), key_buf, value_buf);
+
+                    let previous = self.inner.insert(&key_buf[..], &value_buf[..])?;
+                    if let Some(changes) = &self.changes {
+                        changes.record(
+                            key_buf,
+                            previous.as_ref().map(|v| v.value()),
+                            Some(value_buf),
+                        );
+                    }
+                    Ok::<_, Error>(
+                        previous.map(|v| {
+                            AccessGuard::from(v).with_cipher(self.cipher.as_deref(), key_buf)
+                        }),
+                    )
                 })
             })
-        }?
-        .map(AccessGuard::from))
+        }
     }
 
-    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    #[allow(clippy::type_complexity)]
+    pub fn remove<KQ>(
+        &mut self,
+        key: &KQ,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
+    where
+        K: Borrow<KQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+    {
+        self.try_remove(key).map_err(Error::expect_storage)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn try_remove<KQ>(
+        &mut self,
+        key: &KQ,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
     where
         K: Borrow<KQ>,
-        KQ: bincode::Encode + ?Sized,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
     {
-        Ok(unsafe {
+        unsafe {
             with_encode_key_buf(|key_buf| {
-                let key_size = bincode::encode_into_std_write(key, key_buf, BINCODE_CONFIG)
-                    .expect("encoding can't fail");
-                self.inner.remove(&key_buf[..key_size])
+                KC::encode_into(key, key_buf)?;
+                let previous = self.inner.remove(&key_buf[..])?;
+                if let (Some(changes), Some(previous)) = (&self.changes, &previous) {
+                    changes.record(key_buf, Some(previous.value()), None);
+                }
+                Ok::<_, Error>(
+                    previous
+                        .map(|v| AccessGuard::from(v).with_cipher(self.cipher.as_deref(), key_buf)),
+                )
+            })
+        }
+    }
+
+    /// Remove and return the first key-value pair
+    #[allow(clippy::type_complexity)]
+    pub fn pop_first(
+        &mut self,
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
+        Ok(self.inner.pop_first()?.map(|(k, v)| {
+            if let Some(changes) = &self.changes {
+                changes.record(k.value(), Some(v.value()), None);
+            }
+            let v = AccessGuard::from(v).with_cipher(self.cipher.as_deref(), k.value());
+            (AccessGuard::from(k), v)
+        }))
+    }
+
+    /// Remove and return the last key-value pair
+    #[allow(clippy::type_complexity)]
+    pub fn pop_last(
+        &mut self,
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
+        Ok(self.inner.pop_last()?.map(|(k, v)| {
+            if let Some(changes) = &self.changes {
+                changes.record(k.value(), Some(v.value()), None);
+            }
+            let v = AccessGuard::from(v).with_cipher(self.cipher.as_deref(), k.value());
+            (AccessGuard::from(k), v)
+        }))
+    }
+
+    /// Remove all key-value pairs for which `predicate` returns `true`,
+    /// returning them from the iterator
+    ///
+    /// Only entries read from the iterator are removed. The first entry that
+    /// fails to decode is kept, like all entries after it, and ends the
+    /// iteration with its error.
+    pub fn extract_if<'a, F>(
+        &'a mut self,
+        predicate: F,
+    ) -> Result<ExtractIf<'a, K, V, S, KC, VC>, StorageError>
+    where
+        F: for<'f> FnMut(&'f K, &'f V) -> bool + 'a,
+        K: 'a,
+        V: 'a,
+        KC: 'a,
+        VC: 'a,
+    {
+        self.extract_from_if::<K, _>(.., predicate)
+    }
+
+    /// Like [`Self::extract_if`], but only considering entries within `range`
+    pub fn extract_from_if<'a, Q, F>(
+        &'a mut self,
+        range: impl ops::RangeBounds<Q> + 'a,
+        predicate: F,
+    ) -> Result<ExtractIf<'a, K, V, S, KC, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+        F: for<'f> FnMut(&'f K, &'f V) -> bool + 'a,
+        K: 'a,
+        V: 'a,
+        KC: 'a,
+        VC: 'a,
+    {
+        let extract_if = unsafe {
+            with_encode_key_buf(|start_bound_buf| {
+                let start_bound = encode_bound::<KC, _>(range.start_bound(), start_bound_buf)?;
+
+                with_encode_value_buf(|end_bound_buf| {
+                    let end_bound = encode_bound::<KC, _>(range.end_bound(), end_bound_buf)?;
+
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(ExtractIf::new(
+                        &mut self.inner,
+                        (start_bound, end_bound),
+                        self.cipher.clone(),
+                        predicate,
+                    )?)
+                })
             })
-        }?
-        .map(AccessGuard::from))
+        }
+        .map_err(Error::expect_storage)?;
+        Ok(extract_if.with_changes(self.changes.clone()))
     }
 
-    pub fn retain<F>(&mut self, mut predicate: F) -> Result<(), StorageError>
+    /// Remove all key-value pairs within `range`, returning them from the
+    /// iterator
+    ///
+    /// Only entries read from the iterator are removed. Like with
+    /// [`Self::extract_if`], the first entry that fails to decode is kept and
+    /// ends the iteration with its error.
+    pub fn drain<'a, Q>(
+        &'a mut self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<ExtractIf<'a, K, V, S, KC, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+        K: 'a,
+        V: 'a,
+        KC: 'a,
+        VC: 'a,
+    {
+        self.extract_from_if(range, |_, _| true)
+    }
+
+    pub fn retain<F>(&mut self, predicate: F) -> Result<(), StorageError>
+    where
+        F: for<'f> FnMut(&'f K, &'f V) -> bool,
+    {
+        self.try_retain(predicate).map_err(Error::expect_storage)
+    }
+
+    /// Like [`Self::retain`], but returns an error instead of panicking on
+    /// entries that fail to decode
+    ///
+    /// After the first such entry the predicate is not called anymore and all
+    /// remaining entries are kept. Abort the transaction to discard the entries
+    /// already removed.
+    pub fn try_retain<F>(&mut self, mut predicate: F) -> Result<(), Error>
     where
         F: for<'f> FnMut(&'f K, &'f V) -> bool,
     {
+        let mut res = Ok(());
+        let cipher = self.cipher.as_deref();
+        let changes = self.changes.as_ref();
         self.inner.retain(|raw_key, raw_val| {
-            let k = bincode::decode_from_slice(raw_key, BINCODE_CONFIG)
-                .map(|k| k.0)
-                .expect("Invalid encoding");
-            let v = bincode::decode_from_slice(raw_val, BINCODE_CONFIG)
-                .map(|v| v.0)
-                .expect("Invalid encoding");
-            predicate(&k, &v)
-        })
+            if res.is_err() {
+                return true;
+            }
+            match decode_pair::<K, V, KC, VC>(cipher, raw_key, raw_val) {
+                Ok((k, v)) => retain_recorded(changes, raw_key, raw_val, predicate(&k, &v)),
+                Err(e) => {
+                    res = Err(e);
+                    true
+                }
+            }
+        })?;
+        res
     }
 
     pub fn retain_in<'a, Q, F>(
         &mut self,
         range: impl ops::RangeBounds<Q> + 'a,
-        mut predicate: F,
+        predicate: F,
     ) -> Result<(), StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+        F: for<'f> FnMut(&'f K, &'f V) -> bool,
+    {
+        self.try_retain_in(range, predicate)
+            .map_err(Error::expect_storage)
+    }
+
+    /// Like [`Self::retain_in`], but returns an error instead of panicking,
+    /// see [`Self::try_retain`]
+    pub fn try_retain_in<'a, Q, F>(
+        &mut self,
+        range: impl ops::RangeBounds<Q> + 'a,
+        mut predicate: F,
+    ) -> Result<(), Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
         F: for<'f> FnMut(&'f K, &'f V) -> bool,
     {
+        let mut res = Ok(());
+        let cipher = self.cipher.as_deref();
+        let changes = self.changes.as_ref();
         unsafe {
             with_encode_key_buf(|start_bound_buf| {
-                let start_bound_size = range.start_bound().map(|bound| {
-                    bincode::encode_into_std_write(bound, start_bound_buf, BINCODE_CONFIG)
-                        .expect("encoding can't fail")
-                });
+                let start_bound = encode_bound::<KC, _>(range.start_bound(), start_bound_buf)?;
 
                 with_encode_value_buf(|end_bound_buf| {
-                    let end_bound_size = range.end_bound().map(|bound| {
-                        bincode::encode_into_std_write(bound, end_bound_buf, BINCODE_CONFIG)
-                            .expect("encoding can't fail")
-                    });
-
-                    let start_bound =
-                        start_bound_size.map(|size| SortKey(&start_bound_buf[..size]));
-                    let end_bound = end_bound_size.map(|size| SortKey(&end_bound_buf[..size]));
+                    let end_bound = encode_bound::<KC, _>(range.end_bound(), end_bound_buf)?;
+
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
                     self.inner
                         .retain_in((start_bound, end_bound), |raw_key, raw_val| {
-                            let k = bincode::decode_from_slice(raw_key, BINCODE_CONFIG)
-                                .map(|k| k.0)
-                                .expect("Invalid encoding");
-                            let v = bincode::decode_from_slice(raw_val, BINCODE_CONFIG)
-                                .map(|v| v.0)
-                                .expect("Invalid encoding");
-                            predicate(&k, &v)
+                            if res.is_err() {
+                                return true;
+                            }
+                            match decode_pair::<K, V, KC, VC>(cipher, raw_key, raw_val) {
+                                Ok((k, v)) => {
+                                    retain_recorded(changes, raw_key, raw_val, predicate(&k, &v))
+                                }
+                                Err(e) => {
+                                    res = Err(e);
+                                    true
+                                }
+                            }
                         })?;
 
-                    Ok(())
+                    Ok::<_, Error>(())
                 })
             })
-        }
+        }?;
+        res
     }
 }
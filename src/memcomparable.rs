@@ -0,0 +1,330 @@
+//! Order-preserving ("memcomparable") encoding used by
+//! [`Memcomparable`](crate::Memcomparable)
+//!
+//! For every supported type, comparing encoded bytes lexicographically gives
+//! the same result as comparing the original values with `Ord`:
+//!
+//! * unsigned integers: fixed width, big-endian
+//! * signed integers: fixed width, big-endian, with the sign bit flipped
+//! * `bool`: a single `0`/`1` byte, `char`: as `u32`
+//! * `str`/`String`, `[u8]`/`Vec<u8>`: bytes with every `0x00` escaped as
+//!   `0x00 0xff`, terminated with `0x00 0x00`
+//! * `Option<T>`: `0x00` for `None`, `0x01` followed by `T` for `Some`
+//! * tuples and arrays: concatenation of the elements
+//!
+//! Every encoding is self-delimiting, so composite types compare element by
+//! element. Enums can implement the traits by hand, encoding the variant
+//! index as a `u32` followed by the fields:
+//!
+//! ```
+//! use redb_bincode::memcomparable::{Decode, Encode};
+//! use redb_bincode::DecodeError;
+//!
+//! #[derive(PartialEq, Eq, PartialOrd, Ord)]
+//! enum Id {
+//!     User(u64),
+//!     Group(String),
+//! }
+//!
+//! impl Encode for Id {
+//!     fn encode(&self, buf: &mut Vec<u8>) {
+//!         match self {
+//!             Id::User(id) => (0u32, id).encode(buf),
+//!             Id::Group(name) => (1u32, name).encode(buf),
+//!         }
+//!     }
+//! }
+//!
+//! impl Decode for Id {
+//!     fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+//!         Ok(match u32::decode(input)? {
+//!             0 => Id::User(Decode::decode(input)?),
+//!             1 => Id::Group(Decode::decode(input)?),
+//!             _ => return Err(DecodeError::Other("invalid variant")),
+//!         })
+//!     }
+//! }
+//! ```
+use bincode::error::DecodeError;
+
+/// A type that can be encoded in an order-preserving way
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+/// A type that can be decoded from its order-preserving encoding
+pub trait Decode: Sized {
+    /// Decode a value from the front of `input`, advancing it past the
+    /// consumed bytes
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+fn take<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], DecodeError> {
+    if input.len() < N {
+        return Err(DecodeError::UnexpectedEnd {
+            additional: N - input.len(),
+        });
+    }
+    let (head, rest) = input.split_at(N);
+    *input = rest;
+    Ok(head.try_into().expect("length checked"))
+}
+
+fn encode_bytes(bytes: &[u8], buf: &mut Vec<u8>) {
+    for &b in bytes {
+        buf.push(b);
+        if b == 0 {
+            buf.push(0xff);
+        }
+    }
+    buf.extend_from_slice(&[0, 0]);
+}
+
+fn decode_bytes(input: &mut &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut out = vec![];
+    loop {
+        let [b] = take::<1>(input)?;
+        if b != 0 {
+            out.push(b);
+            continue;
+        }
+        match take::<1>(input)? {
+            [0] => return Ok(out),
+            [0xff] => out.push(0),
+            _ => return Err(DecodeError::Other("invalid memcomparable byte escape")),
+        }
+    }
+}
+
+impl<T> Encode for &T
+where
+    T: Encode + ?Sized,
+{
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (**self).encode(buf)
+    }
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+            }
+
+            impl Decode for $t {
+                fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    Ok(<$t>::from_be_bytes(take(input)?))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_signed {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    let flipped = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                    buf.extend_from_slice(&flipped.to_be_bytes());
+                }
+            }
+
+            impl Decode for $t {
+                fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    let flipped = <$u>::from_be_bytes(take(input)?);
+                    Ok((flipped ^ (1 << (<$u>::BITS - 1))) as $t)
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128);
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+// `usize`/`isize` are always stored as 64 bits, so the encoding does not
+// depend on the platform
+impl Encode for usize {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u64).encode(buf)
+    }
+}
+
+impl Decode for usize {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let v = u64::decode(input)?;
+        usize::try_from(v).map_err(|_| DecodeError::OutsideUsizeRange(v))
+    }
+}
+
+impl Encode for isize {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as i64).encode(buf)
+    }
+}
+
+impl Decode for isize {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        isize::try_from(i64::decode(input)?).map_err(|_| DecodeError::Other("isize out of range"))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(u8::from(*self));
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match take::<1>(input)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            [v] => Err(DecodeError::InvalidBooleanValue(v)),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        u32::from(*self).encode(buf)
+    }
+}
+
+impl Decode for char {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let v = u32::decode(input)?;
+        char::from_u32(v).ok_or(DecodeError::InvalidCharEncoding(v.to_be_bytes()))
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), buf)
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.as_str().encode(buf)
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        String::from_utf8(decode_bytes(input)?).map_err(|e| DecodeError::Utf8 {
+            inner: e.utf8_error(),
+        })
+    }
+}
+
+impl Encode for [u8] {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        encode_bytes(self, buf)
+    }
+}
+
+impl Encode for Vec<u8> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.as_slice().encode(buf)
+    }
+}
+
+impl Decode for Vec<u8> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        decode_bytes(input)
+    }
+}
+
+impl<T> Encode for Option<T>
+where
+    T: Encode,
+{
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            None => buf.push(0),
+            Some(v) => {
+                buf.push(1);
+                v.encode(buf);
+            }
+        }
+    }
+}
+
+impl<T> Decode for Option<T>
+where
+    T: Decode,
+{
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match take::<1>(input)? {
+            [0] => Ok(None),
+            [1] => Ok(Some(T::decode(input)?)),
+            _ => Err(DecodeError::Other("invalid memcomparable option tag")),
+        }
+    }
+}
+
+impl<T, const N: usize> Encode for [T; N]
+where
+    T: Encode,
+{
+    fn encode(&self, buf: &mut Vec<u8>) {
+        for v in self {
+            v.encode(buf);
+        }
+    }
+}
+
+impl<T, const N: usize> Decode for [T; N]
+where
+    T: Decode,
+{
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let mut out = Vec::with_capacity(N);
+        for _ in 0..N {
+            out.push(T::decode(input)?);
+        }
+        Ok(out
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("exactly N elements decoded")))
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident),*) => {
+        impl<$($name),*> Encode for ($($name,)*)
+        where
+            $($name: Encode,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn encode(&self, buf: &mut Vec<u8>) {
+                let ($($name,)*) = self;
+                $($name.encode(buf);)*
+            }
+        }
+
+        impl<$($name),*> Decode for ($($name,)*)
+        where
+            $($name: Decode,)*
+        {
+            #[allow(unused_variables)]
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                Ok(($($name::decode(input)?,)*))
+            }
+        }
+    };
+}
+
+impl_tuple!();
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+impl_tuple!(A, B, C, D, E, F, G);
+impl_tuple!(A, B, C, D, E, F, G, H);
@@ -0,0 +1,409 @@
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::{fmt, ops};
+
+use redb::{ReadableMultimapTable as _, StorageError};
+
+use crate::{
+    encode_bound, with_encode_key_buf, with_encode_value_buf, AccessGuard, Bincode, Codec,
+    CodecEncode, Error, Lexicographical, SortKey, SortOrder,
+};
+
+/// Values stored under a single key of a multimap table, in the order of
+/// their encoding
+pub struct MultimapValue<'a, V, VC = Bincode> {
+    inner: redb::MultimapValue<'a, &'static [u8]>,
+    _v: PhantomData<V>,
+    _vc: PhantomData<VC>,
+}
+
+impl<'a, V, VC> From<redb::MultimapValue<'a, &'static [u8]>> for MultimapValue<'a, V, VC> {
+    fn from(inner: redb::MultimapValue<'a, &'static [u8]>) -> Self {
+        Self {
+            inner,
+            _v: PhantomData,
+            _vc: PhantomData,
+        }
+    }
+}
+
+impl<'a, V, VC> MultimapValue<'a, V, VC> {
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl<'a, V, VC> Iterator for MultimapValue<'a, V, VC> {
+    type Item = Result<AccessGuard<'a, V, &'static [u8], VC>, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(AccessGuard::from))
+    }
+}
+
+impl<'a, V, VC> DoubleEndedIterator for MultimapValue<'a, V, VC> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.map(AccessGuard::from))
+    }
+}
+
+pub struct MultimapRange<'a, K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: redb::MultimapRange<'a, SortKey<S>, &'static [u8]>,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
+}
+
+impl<'a, K, V, S, KC, VC> From<redb::MultimapRange<'a, SortKey<S>, &'static [u8]>>
+    for MultimapRange<'a, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    fn from(inner: redb::MultimapRange<'a, SortKey<S>, &'static [u8]>) -> Self {
+        Self {
+            inner,
+            _k: PhantomData,
+            _v: PhantomData,
+            _kc: PhantomData,
+            _vc: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V, S, KC, VC> Iterator for MultimapRange<'a, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    type Item =
+        Result<(AccessGuard<'a, K, SortKey<S>, KC>, MultimapValue<'a, V, VC>), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.inner
+                .next()?
+                .map(|(k, v)| (AccessGuard::from(k), MultimapValue::from(v))),
+        )
+    }
+}
+
+impl<'a, K, V, S, KC, VC> DoubleEndedIterator for MultimapRange<'a, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(
+            self.inner
+                .next_back()?
+                .map(|(k, v)| (AccessGuard::from(k), MultimapValue::from(v))),
+        )
+    }
+}
+
+pub struct ReadOnlyMultimapTable<K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: redb::ReadOnlyMultimapTable<SortKey<S>, &'static [u8]>,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
+}
+
+impl<K, V, S, KC, VC> From<redb::ReadOnlyMultimapTable<SortKey<S>, &'static [u8]>>
+    for ReadOnlyMultimapTable<K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    fn from(inner: redb::ReadOnlyMultimapTable<SortKey<S>, &'static [u8]>) -> Self {
+        Self {
+            inner,
+            _k: PhantomData,
+            _v: PhantomData,
+            _kc: PhantomData,
+            _vc: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S, KC, VC> ReadOnlyMultimapTable<K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub fn as_raw(&self) -> &redb::ReadOnlyMultimapTable<SortKey<S>, &'static [u8]> {
+        &self.inner
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Result<MultimapValue<'_, V, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_get(key).map_err(Error::expect_storage)
+    }
+
+    pub fn try_get<Q>(&self, key: &Q) -> Result<MultimapValue<'_, V, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        unsafe {
+            Ok(with_encode_key_buf(|buf| {
+                KC::encode_into(key, buf)?;
+                Ok::<_, Error>(self.inner.get(&buf[..])?)
+            })?
+            .into())
+        }
+    }
+
+    pub fn range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<MultimapRange<'_, K, V, S, KC, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_range(range).map_err(Error::expect_storage)
+    }
+
+    pub fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<MultimapRange<'_, K, V, S, KC, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        let redb_range = unsafe {
+            with_encode_key_buf(|start_bound_buf| {
+                let start_bound = encode_bound::<KC, _>(range.start_bound(), start_bound_buf)?;
+
+                with_encode_value_buf(|end_bound_buf| {
+                    let end_bound = encode_bound::<KC, _>(range.end_bound(), end_bound_buf)?;
+
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(self.inner.range((start_bound, end_bound))?)
+                })
+            })?
+        };
+        Ok(MultimapRange::from(redb_range))
+    }
+}
+
+pub struct MultimapTable<'txn, K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: redb::MultimapTable<'txn, SortKey<S>, &'static [u8]>,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
+}
+
+impl<'txn, K, V, S, KC, VC> From<redb::MultimapTable<'txn, SortKey<S>, &'static [u8]>>
+    for MultimapTable<'txn, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    fn from(inner: redb::MultimapTable<'txn, SortKey<S>, &'static [u8]>) -> Self {
+        Self {
+            inner,
+            _k: PhantomData,
+            _v: PhantomData,
+            _kc: PhantomData,
+            _vc: PhantomData,
+        }
+    }
+}
+
+impl<'txn, K, V, S, KC, VC> MultimapTable<'txn, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    KC: Codec<K>,
+    VC: Codec<V>,
+{
+    pub fn as_raw(&self) -> &redb::MultimapTable<'txn, SortKey<S>, &'static [u8]> {
+        &self.inner
+    }
+
+    pub fn as_raw_mut(&mut self) -> &mut redb::MultimapTable<'txn, SortKey<S>, &'static [u8]> {
+        &mut self.inner
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Result<MultimapValue<'_, V, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_get(key).map_err(Error::expect_storage)
+    }
+
+    pub fn try_get<Q>(&self, key: &Q) -> Result<MultimapValue<'_, V, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        unsafe {
+            Ok(with_encode_key_buf(|buf| {
+                KC::encode_into(key, buf)?;
+                Ok::<_, Error>(self.inner.get(&buf[..])?)
+            })?
+            .into())
+        }
+    }
+
+    pub fn range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<MultimapRange<'_, K, V, S, KC, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_range(range).map_err(Error::expect_storage)
+    }
+
+    pub fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<MultimapRange<'_, K, V, S, KC, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        let redb_range = unsafe {
+            with_encode_key_buf(|start_bound_buf| {
+                let start_bound = encode_bound::<KC, _>(range.start_bound(), start_bound_buf)?;
+
+                with_encode_value_buf(|end_bound_buf| {
+                    let end_bound = encode_bound::<KC, _>(range.end_bound(), end_bound_buf)?;
+
+                    let start_bound = start_bound.map(|()| SortKey(&start_bound_buf[..]));
+                    let end_bound = end_bound.map(|()| SortKey(&end_bound_buf[..]));
+                    Ok::<_, Error>(self.inner.range((start_bound, end_bound))?)
+                })
+            })?
+        };
+        Ok(MultimapRange::from(redb_range))
+    }
+
+    /// Add `value` to the values of `key`
+    ///
+    /// Returns `true` if the `key`-`value` pair was already present.
+    pub fn insert<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<bool, StorageError>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+        VC: CodecEncode<VQ>,
+        VQ: ?Sized,
+    {
+        self.try_insert(key, value).map_err(Error::expect_storage)
+    }
+
+    pub fn try_insert<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<bool, Error>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+        VC: CodecEncode<VQ>,
+        VQ: ?Sized,
+    {
+        unsafe {
+            with_encode_key_buf(|key_buf| {
+                KC::encode_into(key, key_buf)?;
+
+                with_encode_value_buf(|value_buf| {
+                    VC::encode_into(value, value_buf)?;
+
+                    Ok(self.inner.insert(&key_buf[..], &value_buf[..])?)
+                })
+            })
+        }
+    }
+
+    /// Remove `value` from the values of `key`
+    ///
+    /// Returns `true` if the `key`-`value` pair was present.
+    pub fn remove<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<bool, StorageError>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+        VC: CodecEncode<VQ>,
+        VQ: ?Sized,
+    {
+        self.try_remove(key, value).map_err(Error::expect_storage)
+    }
+
+    pub fn try_remove<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<bool, Error>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+        VC: CodecEncode<VQ>,
+        VQ: ?Sized,
+    {
+        unsafe {
+            with_encode_key_buf(|key_buf| {
+                KC::encode_into(key, key_buf)?;
+
+                with_encode_value_buf(|value_buf| {
+                    VC::encode_into(value, value_buf)?;
+
+                    Ok(self.inner.remove(&key_buf[..], &value_buf[..])?)
+                })
+            })
+        }
+    }
+
+    /// Remove all values of `key`, returning them
+    pub fn remove_all<KQ>(&mut self, key: &KQ) -> Result<MultimapValue<'_, V, VC>, StorageError>
+    where
+        K: Borrow<KQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+    {
+        self.try_remove_all(key).map_err(Error::expect_storage)
+    }
+
+    pub fn try_remove_all<KQ>(&mut self, key: &KQ) -> Result<MultimapValue<'_, V, VC>, Error>
+    where
+        K: Borrow<KQ>,
+        KC: CodecEncode<KQ>,
+        KQ: ?Sized,
+    {
+        Ok(unsafe {
+            with_encode_key_buf(|key_buf| {
+                KC::encode_into(key, key_buf)?;
+                Ok::<_, Error>(self.inner.remove_all(&key_buf[..])?)
+            })
+        }?
+        .into())
+    }
+}
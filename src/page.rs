@@ -0,0 +1,96 @@
+use std::{fmt, ops};
+
+use crate::{Error, KeyBound, Range, SortKey, SortOrder};
+
+/// Position in a table to resume [`ReadableTable::page`](crate::ReadableTable::page) from
+///
+/// Opaque to callers: the encoded last key of the previous page. Can be
+/// stored or sent to clients with `bincode`, or as bytes with
+/// [`Self::as_bytes`] and [`Self::from_bytes`], and stays valid across
+/// transactions (even if the key itself was removed).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct Cursor(Vec<u8>);
+
+impl Cursor {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Order in which [`ReadableTable::page`](crate::ReadableTable::page) walks the keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A page of decoded entries
+#[derive(Debug, Clone)]
+pub struct Page<K, V> {
+    pub items: Vec<(K, V)>,
+    /// Where the next page starts, `None` if there are no more entries in
+    /// this direction
+    pub next: Option<Cursor>,
+}
+
+impl<K, V> Page<K, V> {
+    pub(crate) fn collect<S, KC, VC>(
+        mut range: Range<'_, K, V, SortKey<S>, KC, VC>,
+        limit: usize,
+        direction: Direction,
+    ) -> Result<Self, Error>
+    where
+        S: SortOrder + fmt::Debug,
+        KC: crate::CodecDecode<K>,
+        VC: crate::CodecDecode<V>,
+    {
+        let mut next = || match direction {
+            Direction::Forward => range.next(),
+            Direction::Backward => range.next_back(),
+        };
+
+        if limit == 0 {
+            return Err(Error::ZeroPageLimit);
+        }
+        // `limit` may be a large "no limit"
+        let mut items = Vec::with_capacity(limit.min(1024));
+        let mut last_key = None;
+        while items.len() < limit {
+            let Some(entry) = next() else {
+                return Ok(Self { items, next: None });
+            };
+            let (k, v) = entry?;
+            items.push((k.value_try()?, v.value_try()?));
+            last_key = Some(k.encoded().to_vec());
+        }
+
+        let more = next().transpose()?.is_some();
+        Ok(Self {
+            items,
+            next: last_key.filter(|_| more).map(Cursor),
+        })
+    }
+}
+
+/// Keys after (or, as an end, before) an encoded [`Cursor`]
+pub(crate) struct CursorBound<'a>(pub(crate) Option<&'a Cursor>);
+
+impl<'a, K, S, KC> KeyBound<K, S, KC> for CursorBound<'a> {
+    fn encode_start(&self, buf: &mut Vec<u8>) -> Result<Option<ops::Bound<()>>, Error> {
+        Ok(Some(<Self as KeyBound<K, S, KC>>::encode_end(self, buf)?))
+    }
+
+    fn encode_end(&self, buf: &mut Vec<u8>) -> Result<ops::Bound<()>, Error> {
+        Ok(match self.0 {
+            Some(cursor) => {
+                buf.extend_from_slice(&cursor.0);
+                ops::Bound::Excluded(())
+            }
+            None => ops::Bound::Unbounded,
+        })
+    }
+}
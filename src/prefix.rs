@@ -0,0 +1,140 @@
+use std::ops;
+
+use crate::{
+    Codec, CodecEncode, Error, Lexicographical, PrefixBound, Range, ReadOnlyTable, SortKey,
+    StorageError, Table,
+};
+
+/// A key codec for which prefix scans are correct
+///
+/// The encoding of a tuple (or a struct) must be the concatenation of the
+/// encodings of its elements, and every encoding must be self-delimiting, so
+/// that a key starts with the encoded prefix exactly if its leading elements
+/// are equal to the prefix. This holds for [`Bincode`](crate::Bincode)
+/// (integers are varints, strings and collections are length-prefixed) and
+/// [`Memcomparable`](crate::Memcomparable) (strings and bytes are
+/// terminated).
+///
+/// It does not hold for codecs that e.g. store strings as raw bytes, where
+/// `"ab"` would be a prefix of `"abc"`.
+pub trait PrefixCodec {}
+
+impl PrefixCodec for crate::Bincode {}
+
+impl PrefixCodec for crate::Memcomparable {}
+
+#[cfg(feature = "serde")]
+impl PrefixCodec for crate::BincodeSerde {}
+
+/// `P` is a leading part of the key type, usable with
+/// [`Table::prefix`](crate::Table::prefix)
+///
+/// Implemented for the leading elements of tuples. Can be implemented for
+/// custom key types whose encoding starts with the encoding of `P`, e.g.
+/// structs whose first field is `P`.
+pub trait KeyPrefix<P> {}
+
+/// Implement [`KeyPrefix`] for all proper leading parts of a tuple: its
+/// first element, then tuples of its first elements
+macro_rules! impl_key_prefix {
+    (@impl [$($all:ident),*] [$first:ident] [$next:ident $(, $rest:ident)*]) => {
+        impl<$($all),*> KeyPrefix<$first> for ($($all,)*) {}
+        impl_key_prefix!(@impl [$($all),*] [$first, $next] [$($rest),*]);
+    };
+    (@impl [$($all:ident),*] [$($prefix:ident),+] [$next:ident $(, $rest:ident)*]) => {
+        impl<$($all),*> KeyPrefix<($($prefix,)+)> for ($($all,)*) {}
+        impl_key_prefix!(@impl [$($all),*] [$($prefix,)+ $next] [$($rest),*]);
+    };
+    (@impl [$($all:ident),*] [$($prefix:ident),+] []) => {};
+    ($first:ident $(, $rest:ident)*) => {
+        impl_key_prefix!(@impl [$first $(, $rest)*] [$first] [$($rest),*]);
+    };
+}
+
+// as many elements as `Memcomparable` tuples
+impl_key_prefix!(A, B);
+impl_key_prefix!(A, B, C);
+impl_key_prefix!(A, B, C, D);
+impl_key_prefix!(A, B, C, D, E);
+impl_key_prefix!(A, B, C, D, E, F);
+impl_key_prefix!(A, B, C, D, E, F, G);
+impl_key_prefix!(A, B, C, D, E, F, G, H);
+
+impl<K, V, KC, VC> ReadOnlyTable<K, V, Lexicographical, KC, VC>
+where
+    KC: Codec<K> + PrefixCodec,
+    VC: Codec<V>,
+{
+    /// All entries whose key starts with `prefix`, in key order
+    ///
+    /// See [`KeyPrefix`] and [`PrefixCodec`] for which prefixes are supported.
+    pub fn prefix<P>(
+        &self,
+        prefix: &P,
+    ) -> Result<Range<'_, K, V, SortKey<Lexicographical>, KC, VC>, StorageError>
+    where
+        K: KeyPrefix<P>,
+        KC: CodecEncode<P>,
+    {
+        self.try_prefix(prefix).map_err(Error::expect_storage)
+    }
+
+    pub fn try_prefix<P>(
+        &self,
+        prefix: &P,
+    ) -> Result<Range<'_, K, V, SortKey<Lexicographical>, KC, VC>, Error>
+    where
+        K: KeyPrefix<P>,
+        KC: CodecEncode<P>,
+    {
+        self.try_range_between(PrefixBound::Included(prefix), PrefixBound::Included(prefix))
+    }
+}
+
+impl<'txn, K, V, KC, VC> Table<'txn, K, V, Lexicographical, KC, VC>
+where
+    KC: Codec<K> + PrefixCodec,
+    VC: Codec<V>,
+{
+    /// All entries whose key starts with `prefix`, in key order
+    ///
+    /// See [`KeyPrefix`] and [`PrefixCodec`] for which prefixes are supported.
+    pub fn prefix<P>(
+        &self,
+        prefix: &P,
+    ) -> Result<Range<'_, K, V, SortKey<Lexicographical>, KC, VC>, StorageError>
+    where
+        K: KeyPrefix<P>,
+        KC: CodecEncode<P>,
+    {
+        self.try_prefix(prefix).map_err(Error::expect_storage)
+    }
+
+    pub fn try_prefix<P>(
+        &self,
+        prefix: &P,
+    ) -> Result<Range<'_, K, V, SortKey<Lexicographical>, KC, VC>, Error>
+    where
+        K: KeyPrefix<P>,
+        KC: CodecEncode<P>,
+    {
+        self.try_range_between(PrefixBound::Included(prefix), PrefixBound::Included(prefix))
+    }
+}
+
+/// Turn an encoded prefix into the exclusive upper bound of all keys starting
+/// with it
+///
+/// Returns `Unbounded` if there is no such bound, i.e. the prefix consists of
+/// `0xff` bytes only.
+pub(crate) fn prefix_successor(buf: &mut Vec<u8>) -> ops::Bound<()> {
+    while let Some(last) = buf.last_mut() {
+        if *last == u8::MAX {
+            buf.pop();
+        } else {
+            *last += 1;
+            return ops::Bound::Excluded(());
+        }
+    }
+    ops::Bound::Unbounded
+}
@@ -1,77 +1,402 @@
-use std::fmt;
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::{fmt, ops};
 
 use redb::StorageError;
 
-use crate::{AccessGuard, SortKey, SortOrder};
+use crate::changes::ChangeRecorder;
+use crate::{
+    AccessGuard, Bincode, CodecDecode, Error, Lexicographical, SortKey, SortOrder, TableCipher,
+};
 
-pub struct Range<'a, K, V, IK = &'static [u8]>
+pub struct Range<'a, K, V, IK = &'static [u8], KC = Bincode, VC = Bincode>
 where
     IK: redb::Value + 'static + redb::Key,
 {
     inner: redb::Range<'a, IK, &'static [u8]>,
+    cipher: Option<&'a TableCipher>,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
 }
 
-impl<'a, S, K, V> From<redb::Range<'a, SortKey<S>, &'static [u8]>> for Range<'a, K, V, SortKey<S>>
+impl<'a, S, K, V, KC, VC> From<redb::Range<'a, SortKey<S>, &'static [u8]>>
+    for Range<'a, K, V, SortKey<S>, KC, VC>
 where
     S: SortOrder + fmt::Debug,
 {
     fn from(inner: redb::Range<'a, SortKey<S>, &'static [u8]>) -> Self {
         Self {
             inner,
+            cipher: None,
             _k: PhantomData,
             _v: PhantomData,
+            _kc: PhantomData,
+            _vc: PhantomData,
         }
     }
 }
 
-impl<'a, K, V> Iterator for Range<'a, K, V, &'static [u8]> {
-    type Item = Result<(AccessGuard<'a, K>, AccessGuard<'a, V>), StorageError>;
+impl<'a, K, V, IK, KC, VC> Range<'a, K, V, IK, KC, VC>
+where
+    IK: redb::Value + 'static + redb::Key,
+{
+    pub(crate) fn with_cipher(mut self, cipher: Option<&'a TableCipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+}
+
+impl<'a, K, V, KC, VC> Iterator for Range<'a, K, V, &'static [u8], KC, VC> {
+    type Item = Result<
+        (
+            AccessGuard<'a, K, &'static [u8], KC>,
+            AccessGuard<'a, V, &'static [u8], VC>,
+        ),
+        StorageError,
+    >;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher, k.value());
+            (AccessGuard::from(k), v)
+        }))
+    }
+}
+
+impl<'a, S, K, V, KC, VC> Iterator for Range<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+{
+    type Item = Result<
+        (
+            AccessGuard<'a, K, SortKey<S>, KC>,
+            AccessGuard<'a, V, &'static [u8], VC>,
+        ),
+        StorageError,
+    >;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher, k.value());
+            (AccessGuard::from(k), v)
+        }))
+    }
+}
+impl<'a, K, V, KC, VC> DoubleEndedIterator for Range<'a, K, V, &'static [u8], KC, VC> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher, k.value());
+            (AccessGuard::from(k), v)
+        }))
+    }
+}
+
+impl<'a, S, K, V, KC, VC> DoubleEndedIterator for Range<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.map(|(k, v)| {
+            let v = AccessGuard::from(v).with_cipher(self.cipher, k.value());
+            (AccessGuard::from(k), v)
+        }))
+    }
+}
+
+impl<'a, S, K, V, KC, VC> Range<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
+{
+    /// Iterate over decoded keys only
+    pub fn keys(self) -> Keys<'a, K, V, SortKey<S>, KC, VC> {
+        Keys { inner: self }
+    }
+
+    /// Iterate over decoded values only
+    pub fn values(self) -> Values<'a, K, V, SortKey<S>, KC, VC> {
+        Values { inner: self }
+    }
+
+    /// Iterate over decoded key-value pairs
+    pub fn decoded(self) -> Decoded<'a, K, V, SortKey<S>, KC, VC> {
+        Decoded { inner: self }
+    }
+}
+
+/// Decoded keys of a [`Range`], see [`Range::keys`]
+pub struct Keys<'a, K, V, IK = &'static [u8], KC = Bincode, VC = Bincode>
+where
+    IK: redb::Value + 'static + redb::Key,
+{
+    inner: Range<'a, K, V, IK, KC, VC>,
+}
+
+impl<'a, S, K, V, KC, VC> Iterator for Keys<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+{
+    type Item = Result<K, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         Some(
             self.inner
                 .next()?
-                .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))),
+                .map_err(Error::from)
+                .and_then(|(k, _)| k.value_try()),
         )
     }
 }
 
-impl<'a, S, K, V> Iterator for Range<'a, K, V, SortKey<S>>
+impl<'a, S, K, V, KC, VC> DoubleEndedIterator for Keys<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(
+            self.inner
+                .next_back()?
+                .map_err(Error::from)
+                .and_then(|(k, _)| k.value_try()),
+        )
+    }
+}
+
+/// Decoded values of a [`Range`], see [`Range::values`]
+pub struct Values<'a, K, V, IK = &'static [u8], KC = Bincode, VC = Bincode>
+where
+    IK: redb::Value + 'static + redb::Key,
+{
+    inner: Range<'a, K, V, IK, KC, VC>,
+}
+
+impl<'a, S, K, V, KC, VC> Iterator for Values<'a, K, V, SortKey<S>, KC, VC>
 where
     S: SortOrder + fmt::Debug,
+    VC: CodecDecode<V>,
 {
-    type Item = Result<(AccessGuard<'a, K, SortKey<S>>, AccessGuard<'a, V>), StorageError>;
+    type Item = Result<V, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         Some(
             self.inner
                 .next()?
-                .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))),
+                .map_err(Error::from)
+                .and_then(|(_, v)| v.value_try()),
         )
     }
 }
-impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V, &'static [u8]> {
+
+impl<'a, S, K, V, KC, VC> DoubleEndedIterator for Values<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    VC: CodecDecode<V>,
+{
     fn next_back(&mut self) -> Option<Self::Item> {
         Some(
             self.inner
                 .next_back()?
-                .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))),
+                .map_err(Error::from)
+                .and_then(|(_, v)| v.value_try()),
         )
     }
 }
 
-impl<'a, S, K, V> DoubleEndedIterator for Range<'a, K, V, SortKey<S>>
+/// Decoded key-value pairs of a [`Range`], see [`Range::decoded`]
+pub struct Decoded<'a, K, V, IK = &'static [u8], KC = Bincode, VC = Bincode>
+where
+    IK: redb::Value + 'static + redb::Key,
+{
+    inner: Range<'a, K, V, IK, KC, VC>,
+}
+
+impl<'a, S, K, V, KC, VC> Iterator for Decoded<'a, K, V, SortKey<S>, KC, VC>
 where
     S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
+{
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.inner
+                .next()?
+                .map_err(Error::from)
+                .and_then(|(k, v)| Ok((k.value_try()?, v.value_try()?))),
+        )
+    }
+}
+
+impl<'a, S, K, V, KC, VC> DoubleEndedIterator for Decoded<'a, K, V, SortKey<S>, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         Some(
             self.inner
                 .next_back()?
-                .map(|(k, v)| (AccessGuard::from(k), AccessGuard::from(v))),
+                .map_err(Error::from)
+                .and_then(|(k, v)| Ok((k.value_try()?, v.value_try()?))),
         )
     }
 }
+
+pub(crate) type RawPredicate<'a> = Box<dyn for<'f> FnMut(&'f [u8], &'f [u8]) -> bool + 'a>;
+
+/// The entry the predicate of an [`ExtractIf`] last selected, decoded, or the
+/// error decoding an entry failed with
+type Selected<K, V> = Rc<RefCell<Option<Result<(K, V), Error>>>>;
+
+/// Wrap `predicate` to decode the raw entries, passing the selected ones to
+/// the [`ExtractIf`] through `selected`
+///
+/// Entries that fail to decode are not selected. Neither are any entries
+/// after them, so the iterator ends with the error.
+fn decoding_predicate<'a, K, V, KC, VC>(
+    cipher: Option<Arc<TableCipher>>,
+    selected: Selected<K, V>,
+    mut predicate: impl for<'f> FnMut(&'f K, &'f V) -> bool + 'a,
+) -> RawPredicate<'a>
+where
+    K: 'a,
+    V: 'a,
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
+{
+    let mut failed = false;
+    Box::new(move |raw_key: &[u8], raw_val: &[u8]| {
+        if failed {
+            return false;
+        }
+        match crate::decode_pair::<K, V, KC, VC>(cipher.as_deref(), raw_key, raw_val) {
+            Ok((k, v)) => {
+                let select = predicate(&k, &v);
+                if select {
+                    *selected.borrow_mut() = Some(Ok((k, v)));
+                }
+                select
+            }
+            Err(e) => {
+                failed = true;
+                *selected.borrow_mut() = Some(Err(e));
+                false
+            }
+        }
+    })
+}
+
+/// Removed, decoded key-value pairs of [`Table::extract_if`](crate::Table::extract_if)
+/// and similar
+///
+/// Only entries actually read from the iterator are removed. The first entry
+/// that fails to decode ends the iteration with its error, and is kept with
+/// all entries after it.
+pub struct ExtractIf<'a, K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: redb::ExtractIf<'a, SortKey<S>, &'static [u8], RawPredicate<'a>>,
+    selected: Selected<K, V>,
+    changes: Option<ChangeRecorder<'a>>,
+    _kc: PhantomData<KC>,
+    _vc: PhantomData<VC>,
+}
+
+impl<'a, S, K, V, KC, VC> ExtractIf<'a, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+{
+    /// Remove the entries of `table` within `range` that decode and match
+    /// `predicate`
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn new(
+        table: &'a mut redb::Table<'_, SortKey<S>, &'static [u8]>,
+        range: (ops::Bound<SortKey<&[u8]>>, ops::Bound<SortKey<&[u8]>>),
+        cipher: Option<Arc<TableCipher>>,
+        predicate: impl for<'f> FnMut(&'f K, &'f V) -> bool + 'a,
+    ) -> Result<Self, StorageError>
+    where
+        K: 'a,
+        V: 'a,
+        KC: CodecDecode<K>,
+        VC: CodecDecode<V>,
+    {
+        let selected = Selected::default();
+        let predicate = decoding_predicate::<K, V, KC, VC>(cipher, selected.clone(), predicate);
+        Ok(Self {
+            inner: table.extract_from_if(range, predicate)?,
+            selected,
+            changes: None,
+            _kc: PhantomData,
+            _vc: PhantomData,
+        })
+    }
+
+    /// Record the removed entries in `changes`
+    pub(crate) fn with_changes(mut self, changes: Option<ChangeRecorder<'a>>) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn take_selected(
+        &self,
+        removed: Option<
+            Result<
+                (
+                    redb::AccessGuard<'a, SortKey<S>>,
+                    redb::AccessGuard<'a, &'static [u8]>,
+                ),
+                StorageError,
+            >,
+        >,
+    ) -> Option<Result<(K, V), Error>> {
+        match removed {
+            Some(Ok((k, v))) => {
+                if let Some(changes) = &self.changes {
+                    changes.record(k.value(), Some(v.value()), None);
+                }
+                self.selected.borrow_mut().take()
+            }
+            Some(Err(e)) => Some(Err(e.into())),
+            // possibly a decoding error
+            None => self.selected.borrow_mut().take(),
+        }
+    }
+}
+
+impl<'a, S, K, V, KC, VC> Iterator for ExtractIf<'a, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
+{
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let removed = self.inner.next();
+        self.take_selected(removed)
+    }
+}
+
+impl<'a, S, K, V, KC, VC> DoubleEndedIterator for ExtractIf<'a, K, V, S, KC, VC>
+where
+    S: SortOrder + fmt::Debug,
+    KC: CodecDecode<K>,
+    VC: CodecDecode<V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let removed = self.inner.next_back();
+        self.take_selected(removed)
+    }
+}
@@ -5,114 +5,306 @@ use redb::StorageError;
 
 type Result<T = (), E = StorageError> = std::result::Result<T, E>;
 
-use crate::{AccessGuard, Lexicographical, Range, ReadOnlyTable, SortKey, SortOrder, Table};
+use crate::page::CursorBound;
+use crate::{
+    AccessGuard, Bincode, Codec, CodecEncode, Cursor, Direction, Error, KeyBound, Lexicographical,
+    Page, Range, ReadOnlyTable, SortKey, SortOrder, Table,
+};
 
-pub trait ReadableTable<K, V, S = Lexicographical>
+pub trait ReadableTable<K, V, S = Lexicographical, KC = Bincode, VC = Bincode>
 where
     S: SortOrder + fmt::Debug + 'static,
-    K: bincode::Encode + bincode::Decode,
-    V: bincode::Encode + bincode::Decode,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
+    fn len(&self) -> Result<u64, StorageError>;
+
+    fn is_empty(&self) -> Result<bool, StorageError>;
+
     #[allow(clippy::type_complexity)]
     fn first(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError>;
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    >;
 
     #[allow(clippy::type_complexity)]
     fn last(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError>;
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    >;
 
     fn range<'a, Q>(
         &self,
         range: impl ops::RangeBounds<Q> + 'a,
-    ) -> Result<Range<'_, K, V, SortKey<S>>, StorageError>
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized;
+
+    fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized;
+
+    fn range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError>;
+
+    fn try_range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error>;
+
+    #[allow(clippy::type_complexity)]
+    fn get<Q>(
+        &self,
+        key: &Q,
+    ) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized;
+        KC: CodecEncode<Q>,
+        Q: ?Sized;
 
-    fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    #[allow(clippy::type_complexity)]
+    fn try_get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized;
+        KC: CodecEncode<Q>,
+        Q: ?Sized;
+
+    /// Up to `limit` decoded entries, starting after `after` (or at the
+    /// first/last key) and walking in `direction`
+    ///
+    /// Pass [`Page::next`] of the returned page as `after` to get the
+    /// following one, possibly in a later transaction. Fails with
+    /// [`Error::ZeroPageLimit`] if `limit` is 0.
+    fn page(
+        &self,
+        after: Option<Cursor>,
+        limit: usize,
+        direction: Direction,
+    ) -> Result<Page<K, V>, Error> {
+        let after = CursorBound(after.as_ref());
+        let range = match direction {
+            Direction::Forward => self.try_range_between(after, ..)?,
+            Direction::Backward => self.try_range_between(.., after)?,
+        };
+        Page::collect(range, limit, direction)
+    }
 }
 
-impl<K, V, S> ReadableTable<K, V, S> for ReadOnlyTable<K, V, S>
+impl<K, V, S, KC, VC> ReadableTable<K, V, S, KC, VC> for ReadOnlyTable<K, V, S, KC, VC>
 where
     S: SortOrder + fmt::Debug + 'static,
-    K: bincode::Encode + bincode::Decode,
-    V: bincode::Encode + bincode::Decode,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
+    fn len(&self) -> Result<u64, StorageError> {
+        self.len()
+    }
+
+    fn is_empty(&self) -> Result<bool, StorageError> {
+        self.is_empty()
+    }
+
     #[allow(clippy::type_complexity)]
     fn first(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
         self.first()
     }
 
     #[allow(clippy::type_complexity)]
     fn last(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
         self.last()
     }
 
     fn range<'a, Q>(
         &self,
         range: impl ops::RangeBounds<Q> + 'a,
-    ) -> Result<Range<'_, K, V, SortKey<S>>, StorageError>
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         self.range(range)
     }
 
-    fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_range(range)
+    }
+
+    fn range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError> {
+        self.range_between(start, end)
+    }
+
+    fn try_range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error> {
+        self.try_range_between(start, end)
+    }
+
+    fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         self.get(key)
     }
+
+    fn try_get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_get(key)
+    }
 }
 
-impl<'txn, K, V, S> ReadableTable<K, V, S> for Table<'txn, K, V, S>
+impl<'txn, K, V, S, KC, VC> ReadableTable<K, V, S, KC, VC> for Table<'txn, K, V, S, KC, VC>
 where
     S: SortOrder + fmt::Debug + 'static,
-    K: bincode::Encode + bincode::Decode,
-    V: bincode::Encode + bincode::Decode,
+    KC: Codec<K>,
+    VC: Codec<V>,
 {
+    fn len(&self) -> Result<u64, StorageError> {
+        self.len()
+    }
+
+    fn is_empty(&self) -> Result<bool, StorageError> {
+        self.is_empty()
+    }
+
     #[allow(clippy::type_complexity)]
     fn first(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
         self.first()
     }
 
     #[allow(clippy::type_complexity)]
     fn last(
         &self,
-    ) -> Result<Option<(AccessGuard<'_, K, SortKey<S>>, AccessGuard<'_, V>)>, StorageError> {
+    ) -> Result<
+        Option<(
+            AccessGuard<'_, K, SortKey<S>, KC>,
+            AccessGuard<'_, V, &'static [u8], VC>,
+        )>,
+        StorageError,
+    > {
         self.last()
     }
 
     fn range<'a, Q>(
         &self,
         range: impl ops::RangeBounds<Q> + 'a,
-    ) -> Result<Range<'_, K, V, SortKey<S>>, StorageError>
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         self.range(range)
     }
 
-    fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    fn try_range<'a, Q>(
+        &self,
+        range: impl ops::RangeBounds<Q> + 'a,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_range(range)
+    }
+
+    fn range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, StorageError> {
+        self.range_between(start, end)
+    }
+
+    fn try_range_between(
+        &self,
+        start: impl KeyBound<K, S, KC>,
+        end: impl KeyBound<K, S, KC>,
+    ) -> Result<Range<'_, K, V, SortKey<S>, KC, VC>, Error> {
+        self.try_range_between(start, end)
+    }
+
+    fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, StorageError>
     where
         K: Borrow<Q>,
-        Q: bincode::Encode + ?Sized,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
     {
         self.get(key)
     }
+
+    fn try_get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V, &'static [u8], VC>>, Error>
+    where
+        K: Borrow<Q>,
+        KC: CodecEncode<Q>,
+        Q: ?Sized,
+    {
+        self.try_get(key)
+    }
 }
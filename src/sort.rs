@@ -1,6 +1,8 @@
 use std::borrow::Borrow;
 use std::fmt;
 
+/// Order of the encoded keys in a table, selected via
+/// [`TableDefinition`](crate::TableDefinition)
 pub trait SortOrder {
     fn compare(data1: &[u8], data2: &[u8]) -> std::cmp::Ordering;
 }
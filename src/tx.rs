@@ -1,86 +1,724 @@
+use std::any::TypeId;
+use std::fmt;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
-use redb::TableError;
+use redb::{
+    Durability, MultimapTableHandle as _, ReadableTable as _, SavepointError, StorageError,
+    TableError, TableHandle as _,
+};
 
 use super::{ReadOnlyTable, Table};
-use crate::sort;
+use crate::changelog;
+use crate::changes::{ChangeRecorder, Listeners, RawChange};
+use crate::encryption::{decrypt_value, encrypt_value_buf};
+use crate::{
+    Bincode, Cipher, Codec, Error, IndexedTable, Lexicographical, MultimapTable,
+    ReadOnlyIndexedTable, ReadOnlyMultimapTable, ReadableTable, SecondaryIndex, SortKey, SortOrder,
+    TableCipher,
+};
 
-pub struct ReadTransaction(redb::ReadTransaction);
+pub struct ReadTransaction {
+    inner: redb::ReadTransaction,
+    cipher: Option<Arc<dyn Cipher>>,
+}
 
 impl From<redb::ReadTransaction> for ReadTransaction {
-    fn from(value: redb::ReadTransaction) -> Self {
-        Self(value)
+    fn from(inner: redb::ReadTransaction) -> Self {
+        Self {
+            inner,
+            cipher: None,
+        }
     }
 }
 
 impl ReadTransaction {
+    pub(crate) fn with_cipher(mut self, cipher: Option<Arc<dyn Cipher>>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
     pub fn as_raw(&self) -> &redb::ReadTransaction {
-        &self.0
+        &self.inner
     }
-    pub fn open_table<K, V>(
+    pub fn open_table<K, V, S, KC, VC>(
         &self,
-        table_def: &TableDefinition<'_, K, V>,
-    ) -> Result<ReadOnlyTable<K, V, sort::Lexicographical>, TableError>
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<ReadOnlyTable<K, V, S, KC, VC>, Error>
     where
-        K: bincode::Encode + bincode::Decode,
-        V: bincode::Encode + bincode::Decode,
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
         Ok(ReadOnlyTable {
             inner: self
-                .0
+                .inner
                 .open_table(redb::TableDefinition::new(table_def.name))?,
+            cipher: table_def.cipher(&self.cipher)?,
             _k: PhantomData,
             _v: PhantomData,
+            _kc: PhantomData,
+            _vc: PhantomData,
         })
     }
+
+    pub fn open_multimap_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<ReadOnlyMultimapTable<K, V, S, KC, VC>, TableError>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        Ok(self
+            .inner
+            .open_multimap_table(redb::MultimapTableDefinition::new(table_def.name))?
+            .into())
+    }
+
+    /// Open a table together with its secondary indexes, for lookups
+    ///
+    /// Fails if any of the indexes does not exist yet.
+    pub fn open_indexed_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+        indexes: &[&dyn SecondaryIndex<V>],
+    ) -> Result<ReadOnlyIndexedTable<K, V, S, KC, VC>, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        let index_tables = indexes
+            .iter()
+            .map(|index| {
+                Ok((
+                    index.name().to_owned(),
+                    self.inner
+                        .open_multimap_table(redb::MultimapTableDefinition::new(index.name()))?,
+                ))
+            })
+            .collect::<Result<_, TableError>>()?;
+        Ok(ReadOnlyIndexedTable::new(
+            self.open_table(table_def)?,
+            index_tables,
+        ))
+    }
+
+    /// Names of all (non-multimap) tables
+    pub fn list_tables(&self) -> Result<impl Iterator<Item = String> + '_, StorageError> {
+        Ok(self
+            .inner
+            .list_tables()?
+            .map(|handle| handle.name().to_owned()))
+    }
+
+    /// Names of all multimap tables
+    pub fn list_multimap_tables(&self) -> Result<impl Iterator<Item = String> + '_, StorageError> {
+        Ok(self
+            .inner
+            .list_multimap_tables()?
+            .map(|handle| handle.name().to_owned()))
+    }
+
+    pub fn table_exists<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<bool, StorageError> {
+        Ok(self.list_tables()?.any(|name| name == table_def.name))
+    }
+
+    pub fn multimap_table_exists<K, V, S, KC, VC>(
+        &self,
+        table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<bool, StorageError> {
+        Ok(self
+            .list_multimap_tables()?
+            .any(|name| name == table_def.name))
+    }
 }
 
-pub struct WriteTransaction(redb::WriteTransaction);
+pub struct WriteTransaction {
+    inner: redb::WriteTransaction,
+    /// Process-unique id, used to tie [`Savepoint`]s to their transaction
+    id: u64,
+    cipher: Option<Arc<dyn Cipher>>,
+    /// Changes made so far, if tracking them or keeping a changelog
+    changes: Option<Mutex<Vec<RawChange>>>,
+    deliver_changes: bool,
+    changelog: bool,
+    listeners: Option<Arc<Listeners>>,
+}
 
 impl From<redb::WriteTransaction> for WriteTransaction {
-    fn from(value: redb::WriteTransaction) -> Self {
-        Self(value)
+    fn from(inner: redb::WriteTransaction) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            inner,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            cipher: None,
+            changes: None,
+            deliver_changes: false,
+            changelog: false,
+            listeners: None,
+        }
+    }
+}
+
+/// A savepoint of a [`WriteTransaction`]
+///
+/// Can only be restored on the transaction it was created (or, for
+/// persistent savepoints, retrieved) on.
+pub struct Savepoint {
+    inner: redb::Savepoint,
+    tx_id: u64,
+    /// Number of tracked changes when the savepoint was created
+    changes_len: usize,
+    /// Whether restoring also rolls back committed transactions
+    persistent: bool,
+}
+
+impl Savepoint {
+    pub fn as_raw(&self) -> &redb::Savepoint {
+        &self.inner
+    }
+}
+
+/// Id of a persistent savepoint, valid across transactions until deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PersistentSavepointId(pub u64);
+
+/// Definition of a table
+///
+/// `S` is the [`SortOrder`] of the encoded keys, and `KC` is the
+/// [codec](crate::Codec) used for keys, e.g.
+/// [`Memcomparable`](crate::Memcomparable) to make the key order match `Ord`.
+/// `VC` is the codec used for values.
+///
+/// Note that `redb` does not persist the sort order, so a table must always
+/// be opened with the same `S`.
+pub struct TableDefinition<'a, K, V, S = Lexicographical, KC = Bincode, VC = Bincode> {
+    name: &'a str,
+    encrypted: bool,
+    _key_type: PhantomData<K>,
+    _value_type: PhantomData<V>,
+    _sort_order: PhantomData<S>,
+    _key_codec: PhantomData<KC>,
+    _value_codec: PhantomData<VC>,
+}
+
+impl<'a, K, V, S, KC, VC> TableDefinition<'a, K, V, S, KC, VC> {
+    pub const fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            encrypted: false,
+            _key_type: PhantomData,
+            _value_type: PhantomData,
+            _sort_order: PhantomData,
+            _key_codec: PhantomData,
+            _value_codec: PhantomData,
+        }
+    }
+
+    pub const fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Encrypt the values (but not the keys) of the table with the
+    /// [`Cipher`] of the database
+    ///
+    /// Opening the table fails with [`Error::MissingCipher`] if the database
+    /// has no cipher set, see [`Builder::set_cipher`](crate::Builder::set_cipher).
+    /// Values are authenticated together with the table name and their key,
+    /// so renaming an encrypted table re-encrypts all its values.
+    pub const fn encrypted(self) -> Self {
+        Self {
+            encrypted: true,
+            ..self
+        }
+    }
+
+    /// The cipher of the table, failing with [`Error::MissingCipher`] if it
+    /// is encrypted but the database has no `cipher`
+    pub(crate) fn cipher(
+        &self,
+        cipher: &Option<Arc<dyn Cipher>>,
+    ) -> Result<Option<Arc<TableCipher>>, Error> {
+        if !self.encrypted {
+            return Ok(None);
+        }
+        let cipher = cipher
+            .clone()
+            .ok_or_else(|| Error::MissingCipher(self.name.to_owned()))?;
+        Ok(Some(Arc::new(TableCipher::new(cipher, self.name))))
     }
 }
 
-pub struct TableDefinition<'a, K, V> {
+/// Definition of a multimap table, mapping each key to a set of values
+///
+/// Values are kept in the order of their encoding. See [`TableDefinition`]
+/// for `S`, `KC` and `VC`.
+pub struct MultimapTableDefinition<'a, K, V, S = Lexicographical, KC = Bincode, VC = Bincode> {
     name: &'a str,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
+    _sort_order: PhantomData<S>,
+    _key_codec: PhantomData<KC>,
+    _value_codec: PhantomData<VC>,
 }
 
-impl<'a, K, V> TableDefinition<'a, K, V> {
+impl<'a, K, V, S, KC, VC> MultimapTableDefinition<'a, K, V, S, KC, VC> {
     pub const fn new(name: &'a str) -> Self {
         Self {
             name,
             _key_type: PhantomData,
             _value_type: PhantomData,
+            _sort_order: PhantomData,
+            _key_codec: PhantomData,
+            _value_codec: PhantomData,
         }
     }
+
+    pub const fn name(&self) -> &'a str {
+        self.name
+    }
 }
 impl WriteTransaction {
+    pub(crate) fn with_cipher(mut self, cipher: Option<Arc<dyn Cipher>>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    pub(crate) fn with_listeners(mut self, listeners: Arc<Listeners>) -> Self {
+        self.listeners = Some(listeners);
+        self
+    }
+
+    /// Append the changes to the changelog on commit
+    pub(crate) fn with_changelog(mut self, enabled: bool) -> Self {
+        self.changelog = enabled;
+        if enabled {
+            self.changes.get_or_insert_with(Mutex::default);
+        }
+        self
+    }
+
     pub fn as_raw(&self) -> &redb::WriteTransaction {
-        &self.0
+        &self.inner
+    }
+
+    pub fn as_raw_mut(&mut self) -> &mut redb::WriteTransaction {
+        &mut self.inner
+    }
+
+    /// Set the durability of the commit (defaults to [`Durability::Immediate`])
+    ///
+    /// Panics if persistent savepoints were created or deleted in this
+    /// transaction.
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.inner.set_durability(durability)
+    }
+
+    /// Enable or disable 2-phase commit (defaults to disabled)
+    ///
+    /// See [`redb::WriteTransaction::set_two_phase_commit`].
+    pub fn set_two_phase_commit(&mut self, enabled: bool) {
+        self.inner.set_two_phase_commit(enabled)
+    }
+
+    /// Enable or disable quick-repair (defaults to disabled)
+    ///
+    /// See [`redb::WriteTransaction::set_quick_repair`].
+    pub fn set_quick_repair(&mut self, enabled: bool) {
+        self.inner.set_quick_repair(enabled)
+    }
+
+    /// Enable or disable change tracking (defaults to disabled)
+    ///
+    /// While enabled, inserts, overwrites and removals made through tables
+    /// opened afterwards with [`Self::open_table`] are recorded, and
+    /// delivered to the [`Database::on_change`](crate::Database::on_change)
+    /// listeners once the transaction is committed. Changes made through
+    /// [`Table::as_raw_mut`] or multimap tables are not tracked. Disabling
+    /// discards the changes recorded so far (unless the database keeps a
+    /// changelog).
+    pub fn set_track_changes(&mut self, enabled: bool) {
+        self.deliver_changes = enabled;
+        if enabled {
+            self.changes.get_or_insert_with(Mutex::default);
+        } else if !self.changelog {
+            self.changes = None;
+        }
     }
 
-    pub fn open_table<K, V>(
+    pub fn open_table<K, V, S, KC, VC>(
         &self,
-        table_def: &TableDefinition<'_, K, V>,
-    ) -> Result<Table<K, V, sort::Lexicographical>, TableError>
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<Table<'_, K, V, S, KC, VC>, Error>
     where
-        K: bincode::Encode + bincode::Decode,
-        V: bincode::Encode + bincode::Decode,
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        if TypeId::of::<S>() != TypeId::of::<Lexicographical>() {
+            self.check_logged(|| {
+                format!(
+                    "opening table {} sorted by {}",
+                    table_def.name,
+                    std::any::type_name::<S>()
+                )
+            })?;
+        }
+        let mut table = self.open_table_untracked(table_def)?;
+        table.changes = self
+            .changes
+            .as_ref()
+            .map(|log| ChangeRecorder::new(log, table_def.name));
+        Ok(table)
+    }
+
+    /// Open a table whose changes are never tracked, for internal tables
+    pub(crate) fn open_table_untracked<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<Table<'_, K, V, S, KC, VC>, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
     {
         Ok(Table {
             inner: self
-                .0
+                .inner
                 .open_table(redb::TableDefinition::new(table_def.name))?,
+            cipher: table_def.cipher(&self.cipher)?,
+            changes: None,
             _k: PhantomData,
             _v: PhantomData,
+            _kc: PhantomData,
+            _vc: PhantomData,
         })
     }
 
+    /// Open a multimap table
+    ///
+    /// Fails with [`Error::NotLogged`] if the database keeps a changelog.
+    pub fn open_multimap_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<MultimapTable<'_, K, V, S, KC, VC>, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        self.check_logged(|| format!("opening multimap table {}", table_def.name))?;
+        Ok(self
+            .inner
+            .open_multimap_table(redb::MultimapTableDefinition::new(table_def.name))?
+            .into())
+    }
+
+    /// Open a table together with its secondary indexes
+    ///
+    /// All modifications made through the returned [`IndexedTable`] update
+    /// `indexes` in this transaction. Indexes are not backfilled, so they
+    /// should be added together with the table.
+    ///
+    /// Fails with [`Error::NotLogged`] if the database keeps a changelog, as
+    /// indexes are multimap tables.
+    pub fn open_indexed_table<'i, K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+        indexes: &[&'i dyn SecondaryIndex<V>],
+    ) -> Result<IndexedTable<'_, 'i, K, V, S, KC, VC>, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        self.check_logged(|| format!("opening indexed table {}", table_def.name))?;
+        let index_tables = indexes
+            .iter()
+            .map(|&index| {
+                Ok((
+                    index,
+                    self.inner
+                        .open_multimap_table(redb::MultimapTableDefinition::new(index.name()))?,
+                ))
+            })
+            .collect::<Result<_, TableError>>()?;
+        Ok(IndexedTable::new(self.open_table(table_def)?, index_tables))
+    }
+
+    /// Open an existing table for reading only
+    ///
+    /// Unlike [`Self::open_table`], fails with [`TableError::TableDoesNotExist`]
+    /// (as [`Error::Table`]) instead of creating the table.
+    pub fn open_table_read_only<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<impl ReadableTable<K, V, S, KC, VC> + use<'_, K, V, S, KC, VC>, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        if !self.table_exists(table_def)? {
+            return Err(TableError::TableDoesNotExist(table_def.name.to_owned()).into());
+        }
+        // never modified, so fine for the changelog with any sort order
+        self.open_table_untracked(table_def)
+    }
+
+    /// Names of all (non-multimap) tables
+    pub fn list_tables(&self) -> Result<impl Iterator<Item = String> + '_, StorageError> {
+        Ok(self
+            .inner
+            .list_tables()?
+            .map(|handle| handle.name().to_owned()))
+    }
+
+    /// Names of all multimap tables
+    pub fn list_multimap_tables(&self) -> Result<impl Iterator<Item = String> + '_, StorageError> {
+        Ok(self
+            .inner
+            .list_multimap_tables()?
+            .map(|handle| handle.name().to_owned()))
+    }
+
+    pub fn table_exists<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<bool, StorageError> {
+        Ok(self.list_tables()?.any(|name| name == table_def.name))
+    }
+
+    pub fn multimap_table_exists<K, V, S, KC, VC>(
+        &self,
+        table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<bool, StorageError> {
+        Ok(self
+            .list_multimap_tables()?
+            .any(|name| name == table_def.name))
+    }
+
+    /// Delete a table, returning `true` if it existed
+    ///
+    /// Fails with [`Error::NotLogged`] if the database keeps a changelog.
+    pub fn delete_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<bool, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+    {
+        self.check_logged(|| format!("deleting table {}", table_def.name))?;
+        Ok(self
+            .inner
+            .delete_table(redb::TableDefinition::<SortKey<S>, &'static [u8]>::new(
+                table_def.name,
+            ))?)
+    }
+
+    /// Delete a multimap table, returning `true` if it existed
+    ///
+    /// Fails with [`Error::NotLogged`] if the database keeps a changelog.
+    pub fn delete_multimap_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<bool, Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+    {
+        self.check_logged(|| format!("deleting multimap table {}", table_def.name))?;
+        Ok(self
+            .inner
+            .delete_multimap_table(
+                redb::MultimapTableDefinition::<SortKey<S>, &'static [u8]>::new(table_def.name),
+            )?)
+    }
+
+    /// Rename a table
+    ///
+    /// Values of encrypted tables are bound to the table name, so they are
+    /// re-encrypted (or decrypted, or encrypted, if only one of the
+    /// definitions is [encrypted](TableDefinition::encrypted)). Fails with
+    /// [`Error::NotLogged`] if the database keeps a changelog.
+    pub fn rename_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+        new_table_def: &TableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<(), Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+    {
+        self.check_logged(|| format!("renaming table {}", table_def.name))?;
+        let cipher = table_def.cipher(&self.cipher)?;
+        let new_cipher = new_table_def.cipher(&self.cipher)?;
+        self.inner.rename_table(
+            redb::TableDefinition::<SortKey<S>, &'static [u8]>::new(table_def.name),
+            redb::TableDefinition::<SortKey<S>, &'static [u8]>::new(new_table_def.name),
+        )?;
+        if cipher.is_none() && new_cipher.is_none() {
+            return Ok(());
+        }
+
+        let mut table =
+            self.inner
+                .open_table(redb::TableDefinition::<SortKey<S>, &'static [u8]>::new(
+                    new_table_def.name,
+                ))?;
+        let mut reencrypted = vec![];
+        for entry in table.iter()? {
+            let (k, v) = entry?;
+            let mut value = decrypt_value(cipher.as_deref(), k.value(), v.value())?.into_owned();
+            encrypt_value_buf(new_cipher.as_deref(), k.value(), &mut value);
+            reencrypted.push((k.value().to_vec(), value));
+        }
+        for (k, v) in reencrypted {
+            table.insert(&k[..], &v[..])?;
+        }
+        Ok(())
+    }
+
+    /// Rename a multimap table
+    ///
+    /// Fails with [`Error::NotLogged`] if the database keeps a changelog.
+    pub fn rename_multimap_table<K, V, S, KC, VC>(
+        &self,
+        table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+        new_table_def: &MultimapTableDefinition<'_, K, V, S, KC, VC>,
+    ) -> Result<(), Error>
+    where
+        S: SortOrder + fmt::Debug + 'static,
+    {
+        self.check_logged(|| format!("renaming multimap table {}", table_def.name))?;
+        Ok(self.inner.rename_multimap_table(
+            redb::MultimapTableDefinition::<SortKey<S>, &'static [u8]>::new(table_def.name),
+            redb::MultimapTableDefinition::<SortKey<S>, &'static [u8]>::new(new_table_def.name),
+        )?)
+    }
+
+    /// Refuse `operation`, which the changelog can't record, if the database
+    /// keeps one
+    pub(crate) fn check_logged(&self, operation: impl FnOnce() -> String) -> Result<(), Error> {
+        if self.changelog {
+            return Err(Error::NotLogged(operation()));
+        }
+        Ok(())
+    }
+
+    /// Commit the transaction, then deliver its tracked changes, see
+    /// [`Self::set_track_changes`]
     pub fn commit(self) -> Result<(), redb::CommitError> {
-        self.0.commit()
+        let changes = self
+            .changes
+            .map(|changes| changes.into_inner().expect("change log poisoned"))
+            .unwrap_or_default();
+        if self.changelog {
+            changelog::append(&self.inner, &changes)?;
+        }
+        self.inner.commit()?;
+        if let (true, Some(listeners)) = (self.deliver_changes, self.listeners) {
+            listeners.deliver(&changes);
+        }
+        Ok(())
+    }
+
+    /// Discard all changes made in this transaction
+    pub fn abort(self) -> Result<(), StorageError> {
+        self.inner.abort()
+    }
+
+    fn changes_len(&self) -> usize {
+        self.changes
+            .as_ref()
+            .map_or(0, |log| log.lock().expect("change log poisoned").len())
+    }
+
+    /// Create a savepoint that lives until the returned [`Savepoint`] is
+    /// dropped
+    ///
+    /// Like in `redb`, savepoints can only be created before any table is
+    /// opened in this transaction.
+    pub fn ephemeral_savepoint(&self) -> Result<Savepoint, SavepointError> {
+        Ok(Savepoint {
+            inner: self.inner.ephemeral_savepoint()?,
+            tx_id: self.id,
+            changes_len: self.changes_len(),
+            persistent: false,
+        })
+    }
+
+    /// Create a savepoint that is persisted in the database until deleted
+    /// with [`Self::delete_persistent_savepoint`]
+    ///
+    /// Requires `Durability::Immediate` or stronger, and no tables opened in
+    /// this transaction.
+    pub fn persistent_savepoint(&self) -> Result<PersistentSavepointId, SavepointError> {
+        Ok(PersistentSavepointId(self.inner.persistent_savepoint()?))
+    }
+
+    /// Get a persistent savepoint, to be restored on this transaction
+    pub fn get_persistent_savepoint(
+        &self,
+        id: PersistentSavepointId,
+    ) -> Result<Savepoint, SavepointError> {
+        Ok(Savepoint {
+            inner: self.inner.get_persistent_savepoint(id.0)?,
+            tx_id: self.id,
+            // created in an earlier transaction (or before opening any table
+            // in this one), so it predates all changes tracked in this one
+            changes_len: 0,
+            persistent: true,
+        })
+    }
+
+    /// Returns `true` if the savepoint existed
+    pub fn delete_persistent_savepoint(
+        &self,
+        id: PersistentSavepointId,
+    ) -> Result<bool, SavepointError> {
+        self.inner.delete_persistent_savepoint(id.0)
+    }
+
+    pub fn list_persistent_savepoints(
+        &self,
+    ) -> Result<impl Iterator<Item = PersistentSavepointId> + '_, StorageError> {
+        Ok(self
+            .inner
+            .list_persistent_savepoints()?
+            .map(PersistentSavepointId))
+    }
+
+    /// Roll back all changes made after `savepoint` was created
+    ///
+    /// All tables of this transaction must be closed. Returns
+    /// [`SavepointError::InvalidSavepoint`] if `savepoint` belongs to another
+    /// transaction.
+    ///
+    /// Tracked changes made after `savepoint` are discarded too. Restoring a
+    /// persistent savepoint also rolls back the transactions committed since
+    /// it was created, which is not reported as changes. That would roll back
+    /// the changelog as well, so it fails with [`Error::NotLogged`] if the
+    /// database keeps one.
+    pub fn restore_savepoint(&mut self, savepoint: &Savepoint) -> Result<(), Error> {
+        if savepoint.tx_id != self.id {
+            return Err(SavepointError::InvalidSavepoint.into());
+        }
+        if savepoint.persistent {
+            self.check_logged(|| "restoring a persistent savepoint".to_owned())?;
+        }
+        self.inner.restore_savepoint(&savepoint.inner)?;
+        if let Some(changes) = &mut self.changes {
+            changes
+                .get_mut()
+                .expect("change log poisoned")
+                .truncate(savepoint.changes_len);
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,204 @@
+use std::{fmt, ops};
+
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use redb::ReadableTable as _;
+
+use crate::encryption::{decrypt_value, encrypt_value_buf};
+use crate::{
+    with_encode_value_buf, Codec, Error, SortOrder, Table, TableDefinition, BINCODE_CONFIG,
+};
+
+/// A value type with a schema version, that can be upgraded from its previous
+/// version
+///
+/// Versions form a chain through [`Self::Previous`], ending with
+/// [`NoPrevious`] for the first version:
+///
+/// ```
+/// use redb_bincode::{NoPrevious, VersionedValue};
+///
+/// #[derive(bincode::Encode, bincode::Decode)]
+/// struct UserV1 {
+///     name: String,
+/// }
+///
+/// impl VersionedValue for UserV1 {
+///     const VERSION: u32 = 1;
+///     type Previous = NoPrevious;
+///
+///     fn upgrade(previous: NoPrevious) -> Self {
+///         match previous {}
+///     }
+/// }
+///
+/// #[derive(bincode::Encode, bincode::Decode)]
+/// struct UserV2 {
+///     name: String,
+///     admin: bool,
+/// }
+///
+/// impl VersionedValue for UserV2 {
+///     const VERSION: u32 = 2;
+///     type Previous = UserV1;
+///
+///     fn upgrade(previous: UserV1) -> Self {
+///         Self {
+///             name: previous.name,
+///             admin: false,
+///         }
+///     }
+/// }
+/// ```
+pub trait VersionedValue: bincode::Encode + bincode::Decode {
+    /// Version tag stored with every value, must be unique within the chain
+    const VERSION: u32;
+
+    /// The previous version of this type
+    type Previous: VersionedValue;
+
+    fn upgrade(previous: Self::Previous) -> Self;
+
+    /// Decode a value stored with `version`, upgrading it as needed
+    fn decode_version<D: Decoder>(version: u32, decoder: &mut D) -> Result<Self, DecodeError> {
+        if version == Self::VERSION {
+            Self::decode(decoder)
+        } else {
+            Self::Previous::decode_version(version, decoder).map(Self::upgrade)
+        }
+    }
+}
+
+/// Terminates the chain of [`VersionedValue::Previous`] types
+#[derive(Debug)]
+pub enum NoPrevious {}
+
+impl bincode::Encode for NoPrevious {
+    fn encode<E: Encoder>(&self, _encoder: &mut E) -> Result<(), EncodeError> {
+        match *self {}
+    }
+}
+
+impl bincode::Decode for NoPrevious {
+    fn decode<D: Decoder>(_decoder: &mut D) -> Result<Self, DecodeError> {
+        Err(DecodeError::EmptyEnum {
+            type_name: std::any::type_name::<Self>(),
+        })
+    }
+}
+
+impl VersionedValue for NoPrevious {
+    const VERSION: u32 = u32::MAX;
+    type Previous = NoPrevious;
+
+    fn upgrade(previous: NoPrevious) -> Self {
+        previous
+    }
+
+    fn decode_version<D: Decoder>(version: u32, _decoder: &mut D) -> Result<Self, DecodeError> {
+        Err(DecodeError::OtherString(format!(
+            "unknown value version: {version}"
+        )))
+    }
+}
+
+/// A value stored together with its [`VersionedValue::VERSION`]
+///
+/// Values of older versions are upgraded when decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Versioned<V>(pub V);
+
+/// A table with [`Versioned`] values
+pub type VersionedTableDefinition<'a, K, V, S = crate::Lexicographical, KC = crate::Bincode> =
+    TableDefinition<'a, K, Versioned<V>, S, KC>;
+
+impl<V> Versioned<V> {
+    pub fn into_inner(self) -> V {
+        self.0
+    }
+}
+
+impl<V> ops::Deref for Versioned<V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<V> ops::DerefMut for Versioned<V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<V> bincode::Encode for Versioned<V>
+where
+    V: VersionedValue,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        V::VERSION.encode(encoder)?;
+        self.0.encode(encoder)
+    }
+}
+
+impl<V> bincode::Decode for Versioned<V>
+where
+    V: VersionedValue,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let version = u32::decode(decoder)?;
+        V::decode_version(version, decoder).map(Self)
+    }
+}
+
+impl<'txn, K, V, S, KC> Table<'txn, K, Versioned<V>, S, KC>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    KC: Codec<K>,
+    V: VersionedValue,
+{
+    /// Rewrite all values stored with an older version in the latest one
+    ///
+    /// Returns the number of upgraded values.
+    pub fn migrate_all(&mut self) -> Result<u64, Error> {
+        let cipher = self.cipher.clone();
+        let mut outdated = vec![];
+        for entry in self.inner.iter()? {
+            let (k, v) = entry?;
+            let v = decrypt_value(cipher.as_deref(), k.value(), v.value())?;
+            let (version, _) = bincode::decode_from_slice::<u32, _>(&v, BINCODE_CONFIG)?;
+            if version != V::VERSION {
+                outdated.push(k.value().to_vec());
+            }
+        }
+
+        for key in &outdated {
+            let value = self
+                .inner
+                .get(&key[..])?
+                .map(|v| {
+                    let v = decrypt_value(cipher.as_deref(), key, v.value())?;
+                    Ok::<_, Error>(
+                        bincode::decode_from_slice::<Versioned<V>, _>(&v, BINCODE_CONFIG)?.0,
+                    )
+                })
+                .transpose()?
+                .expect("key was just listed");
+            unsafe {
+                with_encode_value_buf(|value_buf| {
+                    bincode::encode_into_std_write(&value, value_buf, BINCODE_CONFIG)?;
+                    encrypt_value_buf(cipher.as_deref(), key, value_buf);
+                    let previous = self.inner.insert(&key[..], &value_buf[..])?;
+                    if let Some(changes) = &self.changes {
+                        changes.record(key, previous.as_ref().map(|v| v.value()), Some(value_buf));
+                    }
+                    Ok::<_, Error>(())
+                })?;
+            }
+        }
+
+        Ok(outdated.len() as u64)
+    }
+}
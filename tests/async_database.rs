@@ -0,0 +1,34 @@
+use redb_bincode::{AsyncDatabase, Database, Error, TableDefinition};
+
+const TABLE: TableDefinition<u64, u64> = TableDefinition::new("table");
+
+#[tokio::test(flavor = "current_thread")]
+async fn concurrent_writers_are_serialized() -> anyhow::Result<()> {
+    let db = AsyncDatabase::from(Database::in_memory()?);
+
+    let writers = (0..10u64).map(|_| {
+        let db = db.clone();
+        tokio::spawn(async move {
+            db.write(|tx| {
+                let mut table = tx.open_table(&TABLE)?;
+                let count = table.get(&0)?.map(|v| v.value()).unwrap_or_default();
+                table.insert(&0, &(count + 1))?;
+                Ok::<_, Error>(())
+            })
+            .await
+        })
+    });
+    for writer in writers.collect::<Vec<_>>() {
+        writer.await??;
+    }
+
+    let count = db
+        .read(|tx| {
+            let table = tx.open_table(&TABLE)?;
+            let count = table.get(&0)?.map(|v| v.value());
+            Ok::<_, Error>(count)
+        })
+        .await?;
+    assert_eq!(count, Some(10));
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use redb_bincode::{BorrowAs, Database, TableDefinition};
+
+#[derive(bincode::Encode, bincode::Decode)]
+struct Document {
+    id: u64,
+    body: String,
+}
+
+#[derive(bincode::BorrowDecode)]
+struct DocumentRef<'a> {
+    id: u64,
+    body: &'a str,
+}
+
+impl BorrowAs<DocumentRef<'_>> for Document {}
+
+const DOCS: TableDefinition<String, Document> = TableDefinition::new("docs");
+
+#[test]
+fn borrowed_keys_and_values() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    tx.open_table(&DOCS)?.insert(
+        "readme",
+        &Document {
+            id: 1,
+            body: "hello".repeat(100),
+        },
+    )?;
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&DOCS)?;
+
+    let guard = table.get("readme")?.expect("present");
+    let doc: DocumentRef = guard.value_borrowed();
+    assert_eq!(doc.id, 1);
+    assert!(doc.body.starts_with("hellohello"));
+
+    for entry in table.range::<str>(..)? {
+        let (k, v) = entry?;
+        assert_eq!(k.value_borrowed::<&str>(), "readme");
+        assert_eq!(v.value_borrowed::<DocumentRef>().body.len(), 500);
+    }
+    Ok(())
+}
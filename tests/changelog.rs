@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use redb_bincode::{
+    Database, Error, IndexDefinition, MultimapTableDefinition, SortOrder, TableDefinition,
+};
+
+const USERS: TableDefinition<u32, String> = TableDefinition::new("users");
+
+struct TempPath(PathBuf);
+
+impl TempPath {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("redb-bincode-{}-{name}.redb", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn users(db: &Database) -> anyhow::Result<Vec<(u32, String)>> {
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&USERS)?;
+    Ok(table
+        .range::<u32>(..)?
+        .decoded()
+        .collect::<Result<_, _>>()?)
+}
+
+#[test]
+fn replicate_through_changelog() -> anyhow::Result<()> {
+    let (primary_path, replica_path) = (TempPath::new("primary"), TempPath::new("replica"));
+    let primary = Database::builder()
+        .set_changelog(true)
+        .create(&primary_path.0)?;
+    let replica = Database::create(&replica_path.0)?;
+
+    primary.write(|tx| {
+        let mut users = tx.open_table(&USERS)?;
+        users.insert(&1, "a")?;
+        users.insert(&2, "b")?;
+        Ok::<_, Error>(())
+    })?;
+    // aborted changes are not logged
+    let tx = primary.begin_write()?;
+    tx.open_table(&USERS)?.insert(&3, "c")?;
+    tx.abort()?;
+    primary.write(|tx| {
+        let mut users = tx.open_table(&USERS)?;
+        users.insert(&1, "a2")?;
+        users.remove(&2)?;
+        users.insert(&4, "d")?;
+        Ok::<_, Error>(())
+    })?;
+
+    let log = primary.read_changelog(0, 100)?;
+    assert_eq!(
+        log.iter().map(|entry| entry.seq).collect::<Vec<_>>(),
+        vec![0, 1, 2, 3, 4]
+    );
+    assert!(log.iter().all(|entry| entry.table == "users"));
+    assert_eq!(log[3].value, None);
+
+    // in batches, re-applying some entries
+    replica.apply_changelog(&primary.read_changelog(0, 2)?)?;
+    assert_eq!(replica.applied_changelog()?, 2);
+    replica.apply_changelog(&primary.read_changelog(1, 2)?)?;
+    let next = replica.applied_changelog()?;
+    replica.apply_changelog(&primary.read_changelog(next, 100)?)?;
+    assert_eq!(replica.applied_changelog()?, 5);
+    assert_eq!(users(&replica)?, users(&primary)?);
+    assert_eq!(
+        users(&replica)?,
+        vec![(1, "a2".to_owned()), (4, "d".to_owned())]
+    );
+
+    // missing entries are detected
+    primary.write(|tx| {
+        let mut users = tx.open_table(&USERS)?;
+        users.insert(&5, "e")?;
+        users.insert(&6, "f")?;
+        Ok::<_, Error>(())
+    })?;
+    assert!(matches!(
+        replica.apply_changelog(&primary.read_changelog(6, 100)?),
+        Err(Error::ChangelogGap {
+            expected: 5,
+            found: 6
+        })
+    ));
+
+    primary.truncate_changelog(5)?;
+    let log = primary.read_changelog(0, 100)?;
+    assert_eq!(
+        log.iter().map(|entry| entry.seq).collect::<Vec<_>>(),
+        vec![5, 6]
+    );
+    replica.apply_changelog(&log)?;
+
+    // the sequence continues after truncating everything
+    primary.truncate_changelog(u64::MAX)?;
+    primary.write(|tx| {
+        tx.open_table(&USERS)?.remove(&5)?;
+        Ok::<_, Error>(())
+    })?;
+    replica.apply_changelog(&primary.read_changelog(replica.applied_changelog()?, 100)?)?;
+    drop(replica);
+
+    // the replica survives reopening
+    let replica = Database::open(&replica_path.0)?;
+    assert_eq!(replica.applied_changelog()?, 8);
+    assert_eq!(users(&replica)?, users(&primary)?);
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Reversed;
+
+impl SortOrder for Reversed {
+    fn compare(data1: &[u8], data2: &[u8]) -> std::cmp::Ordering {
+        data2.cmp(data1)
+    }
+}
+
+#[test]
+fn unlogged_operations_are_refused() -> anyhow::Result<()> {
+    const REVERSED: TableDefinition<u32, String, Reversed> = TableDefinition::new("reversed");
+    const TAGS: MultimapTableDefinition<u32, String> = MultimapTableDefinition::new("tags");
+    const USERS_BY_NAME: IndexDefinition<u32, String, String> =
+        IndexDefinition::new("users_by_name", |name: &String| name.clone());
+    const RENAMED: TableDefinition<u32, String> = TableDefinition::new("renamed");
+
+    let db = Database::builder()
+        .set_changelog(true)
+        .create_with_backend(redb::backends::InMemoryBackend::new())?;
+
+    let tx = db.begin_write()?;
+    tx.open_table(&USERS)?.insert(&1, "a")?;
+    assert!(matches!(
+        tx.open_table(&REVERSED).map(drop),
+        Err(Error::NotLogged(_))
+    ));
+    assert!(matches!(
+        tx.open_multimap_table(&TAGS).map(drop),
+        Err(Error::NotLogged(_))
+    ));
+    assert!(matches!(
+        tx.open_indexed_table(&USERS, &[&USERS_BY_NAME]).map(drop),
+        Err(Error::NotLogged(_))
+    ));
+    assert!(matches!(
+        tx.rename_table(&USERS, &RENAMED),
+        Err(Error::NotLogged(_))
+    ));
+    assert!(matches!(tx.delete_table(&USERS), Err(Error::NotLogged(_))));
+    // reading is fine with any sort order
+    assert!(matches!(
+        tx.open_table_read_only(&REVERSED).map(drop),
+        Err(Error::Table(redb::TableError::TableDoesNotExist(_)))
+    ));
+    tx.commit()?;
+
+    assert_eq!(users(&db)?, vec![(1, "a".to_owned())]);
+    assert_eq!(db.read_changelog(0, 100)?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn persistent_savepoints_are_not_restored() -> anyhow::Result<()> {
+    let db = Database::builder()
+        .set_changelog(true)
+        .create_with_backend(redb::backends::InMemoryBackend::new())?;
+
+    let tx = db.begin_write()?;
+    let id = tx.persistent_savepoint()?;
+    tx.commit()?;
+    db.write(|tx| {
+        tx.open_table(&USERS)?.insert(&1, "a")?;
+        Ok::<_, Error>(())
+    })?;
+
+    let mut tx = db.begin_write()?;
+    let savepoint = tx.get_persistent_savepoint(id)?;
+    assert!(matches!(
+        tx.restore_savepoint(&savepoint),
+        Err(Error::NotLogged(_))
+    ));
+    tx.abort()?;
+
+    // ephemeral savepoints only roll back this transaction
+    let mut tx = db.begin_write()?;
+    let savepoint = tx.ephemeral_savepoint()?;
+    tx.open_table(&USERS)?.insert(&2, "b")?;
+    tx.restore_savepoint(&savepoint)?;
+    tx.commit()?;
+
+    assert_eq!(db.read_changelog(0, 100)?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn existing_changelog_is_kept() -> anyhow::Result<()> {
+    let path = TempPath::new("reopened");
+    let db = Database::builder().set_changelog(true).create(&path.0)?;
+    db.write(|tx| {
+        tx.open_table(&USERS)?.insert(&1, "a")?;
+        Ok::<_, Error>(())
+    })?;
+    drop(db);
+
+    let db = Database::open(&path.0)?;
+    db.write(|tx| {
+        tx.open_table(&USERS)?.insert(&2, "b")?;
+        Ok::<_, Error>(())
+    })?;
+    assert_eq!(
+        db.read_changelog(0, 100)?
+            .iter()
+            .map(|entry| entry.seq)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    Ok(())
+}
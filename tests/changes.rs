@@ -0,0 +1,226 @@
+use std::sync::{Arc, Mutex};
+
+use redb_bincode::{Change, Database, Error, TableDefinition};
+
+const USERS: TableDefinition<u32, String> = TableDefinition::new("users");
+const OTHER: TableDefinition<u32, String> = TableDefinition::new("other");
+
+type Seen = Arc<Mutex<Vec<Vec<Change<u32, String>>>>>;
+
+fn listen(db: &Database) -> anyhow::Result<Seen> {
+    let seen = Arc::new(Mutex::new(vec![]));
+    db.on_change(&USERS, {
+        let seen = seen.clone();
+        move |changes: &[Result<Change<u32, String>, Error>]| {
+            let changes = changes.iter().map(|c| c.as_ref().expect("decodes").clone());
+            seen.lock().unwrap().push(changes.collect())
+        }
+    })?;
+    Ok(seen)
+}
+
+#[test]
+fn committed_changes_are_delivered() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+    let seen = listen(&db)?;
+
+    let mut tx = db.begin_write()?;
+    tx.set_track_changes(true);
+    {
+        let mut users = tx.open_table(&USERS)?;
+        users.insert(&1, "a")?;
+        users.insert(&2, "b")?;
+        users.insert(&1, "a2")?;
+        users.remove(&2)?;
+        users.remove(&3)?;
+        users.insert(&4, "d")?;
+        users.insert(&5, "e")?;
+        users.retain(|k, _| *k != 4)?;
+        users.retain_in(5.., |_, _| false)?;
+
+        tx.open_table(&OTHER)?.insert(&1, "other")?;
+    }
+    assert!(seen.lock().unwrap().is_empty());
+    tx.commit()?;
+
+    let s = String::from;
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![vec![
+            Change::Insert {
+                key: 1,
+                value: s("a")
+            },
+            Change::Insert {
+                key: 2,
+                value: s("b")
+            },
+            Change::Overwrite {
+                key: 1,
+                old: s("a"),
+                new: s("a2")
+            },
+            Change::Remove {
+                key: 2,
+                value: s("b")
+            },
+            Change::Insert {
+                key: 4,
+                value: s("d")
+            },
+            Change::Insert {
+                key: 5,
+                value: s("e")
+            },
+            Change::Remove {
+                key: 4,
+                value: s("d")
+            },
+            Change::Remove {
+                key: 5,
+                value: s("e")
+            },
+        ]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn uncommitted_changes_are_not_delivered() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+    let seen = listen(&db)?;
+
+    // aborted
+    let mut tx = db.begin_write()?;
+    tx.set_track_changes(true);
+    tx.open_table(&USERS)?.insert(&1, "a")?;
+    tx.abort()?;
+
+    // dropped
+    let mut tx = db.begin_write()?;
+    tx.set_track_changes(true);
+    tx.open_table(&USERS)?.insert(&1, "a")?;
+    drop(tx);
+
+    // failed closure
+    let res: Result<(), redb_bincode::Error> = db.write(|tx| {
+        tx.set_track_changes(true);
+        tx.open_table(&USERS)?.insert(&1, "a")?;
+        Err(redb_bincode::Error::UniqueIndex("test".into()))
+    });
+    assert!(res.is_err());
+
+    // not tracked
+    let tx = db.begin_write()?;
+    tx.open_table(&USERS)?.insert(&1, "a")?;
+    tx.commit()?;
+
+    assert!(seen.lock().unwrap().is_empty());
+
+    // rolled back to a savepoint
+    let mut tx = db.begin_write()?;
+    tx.set_track_changes(true);
+    let savepoint = tx.ephemeral_savepoint()?;
+    tx.open_table(&USERS)?.insert(&3, "c")?;
+    tx.restore_savepoint(&savepoint)?;
+    tx.open_table(&USERS)?.insert(&2, "b")?;
+    tx.commit()?;
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![vec![Change::Insert {
+            key: 2,
+            value: "b".to_owned()
+        }]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn restoring_a_persistent_savepoint_discards_tracked_changes() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+    let seen = listen(&db)?;
+
+    let tx = db.begin_write()?;
+    let id = tx.persistent_savepoint()?;
+    tx.commit()?;
+
+    let mut tx = db.begin_write()?;
+    tx.set_track_changes(true);
+    tx.open_table(&USERS)?.insert(&1, "a")?;
+    let savepoint = tx.get_persistent_savepoint(id)?;
+    tx.restore_savepoint(&savepoint)?;
+    tx.open_table(&USERS)?.insert(&2, "b")?;
+    tx.delete_persistent_savepoint(id)?;
+    tx.commit()?;
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![vec![Change::Insert {
+            key: 2,
+            value: "b".to_owned()
+        }]]
+    );
+    Ok(())
+}
+
+#[test]
+fn undecodable_changes_are_delivered_as_errors() -> anyhow::Result<()> {
+    const NUMBERS: TableDefinition<u32, u64> = TableDefinition::new("users");
+
+    let db = Database::in_memory()?;
+    let seen = Arc::new(Mutex::new(vec![]));
+    db.on_change(&USERS, {
+        let seen = seen.clone();
+        move |changes: &[Result<Change<u32, String>, Error>]| {
+            let changes = changes.iter().map(|c| c.as_ref().ok().cloned());
+            seen.lock().unwrap().extend(changes)
+        }
+    })?;
+
+    db.write(|tx| {
+        tx.set_track_changes(true);
+        tx.open_table(&NUMBERS)?.insert(&1, &5)?;
+        tx.open_table(&USERS)?.insert(&2, "b")?;
+        Ok::<_, Error>(())
+    })?;
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            None,
+            Some(Change::Insert {
+                key: 2,
+                value: "b".to_owned()
+            })
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn listeners_can_be_removed() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+    let calls = Arc::new(Mutex::new(0));
+    let id = db.on_change(&USERS, {
+        let calls = calls.clone();
+        move |_| *calls.lock().unwrap() += 1
+    })?;
+
+    let write = |key| {
+        db.write(|tx| {
+            tx.set_track_changes(true);
+            tx.open_table(&USERS)?.insert(&key, "a")?;
+            Ok::<_, redb_bincode::Error>(())
+        })
+    };
+    write(1)?;
+    assert!(db.remove_change_listener(id));
+    assert!(!db.remove_change_listener(id));
+    write(2)?;
+    assert_eq!(*calls.lock().unwrap(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use redb::backends::InMemoryBackend;
+use redb::StorageBackend;
+use redb_bincode::{Database, Error, StorageError, TableDefinition};
+
+const TABLE: TableDefinition<u64, u64> = TableDefinition::new("table");
+
+#[derive(Debug, thiserror::Error)]
+enum AppError {
+    #[error(transparent)]
+    Db(#[from] Error),
+    #[error("rejected")]
+    Rejected,
+}
+
+#[test]
+fn write_commits_on_ok_and_aborts_on_err() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let prev = db.write(|tx| {
+        let mut table = tx.open_table(&TABLE)?;
+        table.insert(&1, &1)?;
+        let prev = table.insert(&1, &2)?.map(|v| v.value());
+        Ok::<_, Error>(prev)
+    })?;
+    assert_eq!(prev, Some(1));
+
+    let res = db.write(|tx| {
+        let mut table = tx.open_table(&TABLE)?;
+        table.insert(&2, &2).map_err(Error::from)?;
+        Err::<(), _>(AppError::Rejected)
+    });
+    assert!(matches!(res, Err(AppError::Rejected)));
+
+    db.write(|tx| {
+        tx.open_table(&TABLE)?.insert(&3, &3)?;
+        Ok::<_, Error>(())
+    })?;
+
+    let values = db.read(|tx| {
+        let table = tx.open_table(&TABLE)?;
+        Ok::<_, Error>([1, 2, 3].map(|k| table.get(&k).unwrap().map(|v| v.value())))
+    })?;
+    assert_eq!(values, [Some(2), None, Some(3)]);
+    Ok(())
+}
+
+/// In-memory storage, failing the next sync with `Interrupted` when armed
+#[derive(Debug)]
+struct FlakyBackend {
+    inner: InMemoryBackend,
+    fail_sync: Arc<AtomicBool>,
+}
+
+impl StorageBackend for FlakyBackend {
+    fn len(&self) -> Result<u64, io::Error> {
+        self.inner.len()
+    }
+
+    fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>, io::Error> {
+        self.inner.read(offset, len)
+    }
+
+    fn set_len(&self, len: u64) -> Result<(), io::Error> {
+        self.inner.set_len(len)
+    }
+
+    fn sync_data(&self, eventual: bool) -> Result<(), io::Error> {
+        if self.fail_sync.swap(false, Ordering::SeqCst) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        self.inner.sync_data(eventual)
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+        self.inner.write(offset, data)
+    }
+}
+
+#[test]
+fn write_returns_commit_errors() -> anyhow::Result<()> {
+    let fail_sync = Arc::new(AtomicBool::new(false));
+    let db = Database::builder().create_with_backend(FlakyBackend {
+        inner: InMemoryBackend::new(),
+        fail_sync: fail_sync.clone(),
+    })?;
+
+    fail_sync.store(true, Ordering::SeqCst);
+    let res = db.write(|tx| {
+        tx.open_table(&TABLE)?.insert(&1, &1)?;
+        Ok::<_, Error>(())
+    });
+    assert!(
+        matches!(
+            &res,
+            Err(Error::Commit(redb::CommitError::Storage(StorageError::Io(e))))
+                if e.kind() == io::ErrorKind::Interrupted
+        ),
+        "{res:?}"
+    );
+    // redb fails everything after an I/O error with `PreviousIo`
+    assert!(matches!(
+        db.begin_write().map(drop),
+        Err(redb::TransactionError::Storage(StorageError::PreviousIo))
+    ));
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use redb::ReadableTable as _;
+use redb_bincode::{
+    CodecDecode, CodecEncode, Database, DecodeError, EncodeError, Lexicographical, Memcomparable,
+    MultimapTableDefinition, TableDefinition,
+};
+
+/// Stores strings as their raw UTF-8 bytes
+struct Utf8;
+
+impl CodecEncode<String> for Utf8 {
+    fn encode_into(value: &String, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        buf.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+}
+
+impl CodecEncode<str> for Utf8 {
+    fn encode_into(value: &str, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        buf.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+}
+
+impl CodecDecode<String> for Utf8 {
+    fn decode(bytes: &[u8]) -> Result<String, DecodeError> {
+        String::from_utf8(bytes.to_vec()).map_err(|e| DecodeError::Utf8 {
+            inner: e.utf8_error(),
+        })
+    }
+}
+
+const NAMES: TableDefinition<i64, String, Lexicographical, Memcomparable, Utf8> =
+    TableDefinition::new("names");
+const TAGS: MultimapTableDefinition<i64, String, Lexicographical, Memcomparable, Utf8> =
+    MultimapTableDefinition::new("tags");
+
+#[test]
+fn separate_key_and_value_codecs() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut names = tx.open_table(&NAMES)?;
+        names.insert(&-1, "minus one")?;
+        names.insert(&1, "one")?;
+
+        let raw = names.as_raw().get(&(-1i64 ^ i64::MIN).to_be_bytes()[..])?;
+        assert_eq!(raw.map(|v| v.value().to_vec()), Some(b"minus one".to_vec()));
+
+        let mut tags = tx.open_multimap_table(&TAGS)?;
+        tags.insert(&1, "b")?;
+        tags.insert(&1, "a")?;
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let names = tx.open_table(&NAMES)?;
+    let all = names.range(..)?.decoded().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(all, [(-1, "minus one".to_owned()), (1, "one".to_owned())]);
+
+    let tags = tx.open_multimap_table(&TAGS)?;
+    let tags = tags
+        .get(&1)?
+        .map(|v| v.map(|v| v.value()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(tags, ["a", "b"]);
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use redb::ReadableTable as _;
+use redb_bincode::{
+    Bincode, Compressed, Compression, Database, DecodeError, Error, Lexicographical, Lz4,
+    TableDefinition, Zstd,
+};
+
+const ZSTD: TableDefinition<u64, String, Lexicographical, Bincode, Compressed<Zstd>> =
+    TableDefinition::new("table");
+const LZ4: TableDefinition<u64, String, Lexicographical, Bincode, Compressed<Lz4>> =
+    TableDefinition::new("table");
+
+#[test]
+fn compressed_values() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+    let large = "abc".repeat(1000);
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&ZSTD)?;
+        table.insert(&1, "tiny")?;
+        table.insert(&2, &large)?;
+
+        let raw_len = |k: u64| -> anyhow::Result<usize> {
+            let raw = table.as_raw().get(&[k as u8][..])?.expect("present");
+            Ok(raw.value().len())
+        };
+        // stored as a plain length-prefixed string
+        assert_eq!(raw_len(1)?, 1 + 4);
+        assert!(raw_len(2)? < large.len() / 10);
+    }
+    tx.commit()?;
+
+    // Switching the algorithm keeps existing values readable
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&LZ4)?;
+        table.insert(&3, &large)?;
+        let values = table.range(..)?.values().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(values, ["tiny".to_owned(), large.clone(), large.clone()]);
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[test]
+fn plain_values_stay_readable() -> anyhow::Result<()> {
+    const PLAIN: TableDefinition<u64, String> = TableDefinition::new("plain");
+    const COMPRESSED: TableDefinition<u64, String, Lexicographical, Bincode, Compressed<Zstd>> =
+        TableDefinition::new("plain");
+    // plain bytes looking like the header of compressed values
+    const BYTES: TableDefinition<u64, [u8; 5], Lexicographical, Bincode, Compressed<Zstd>> =
+        TableDefinition::new("bytes");
+
+    let db = Database::in_memory()?;
+    let large = "abc".repeat(1000);
+    let tricky = *b"\xffRBC\x01";
+    db.write(|tx| {
+        let mut plain = tx.open_table(&PLAIN)?;
+        plain.insert(&1, "tiny")?;
+        plain.insert(&2, &large)?;
+        drop(plain);
+        tx.open_table(&COMPRESSED)?.insert(&3, &large)?;
+        tx.open_table(&BYTES)?.insert(&1, &tricky)?;
+        Ok::<_, Error>(())
+    })?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&COMPRESSED)?;
+    let values = table.range(..)?.values().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(values, ["tiny".to_owned(), large.clone(), large.clone()]);
+    assert_eq!(
+        tx.open_table(&BYTES)?.get(&1)?.map(|v| v.value()),
+        Some(tricky)
+    );
+    Ok(())
+}
+
+#[test]
+fn decompressed_size_is_capped() -> anyhow::Result<()> {
+    const LARGE: TableDefinition<u64, String, Lexicographical, Bincode, Compressed<Lz4>> =
+        TableDefinition::new("table");
+    const CAPPED: TableDefinition<
+        u64,
+        String,
+        Lexicographical,
+        Bincode,
+        Compressed<Lz4, Bincode, 64, 1024>,
+    > = TableDefinition::new("table");
+
+    let db = Database::in_memory()?;
+    db.write(|tx| {
+        tx.open_table(&LARGE)?.insert(&1, &"abc".repeat(1000))?;
+        Ok::<_, Error>(())
+    })?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&CAPPED)?;
+    assert!(matches!(
+        table.try_get(&1)?.map(|v| v.value_try()),
+        Some(Err(Error::Decode(_)))
+    ));
+    Ok(())
+}
+
+/// Run-length encoding, as `(count, byte)` pairs
+struct RunLength;
+
+impl Compression for RunLength {
+    const TAG: u8 = 7;
+
+    fn compress(input: &[u8]) -> Vec<u8> {
+        let mut output = vec![];
+        for &b in input {
+            match output.len().checked_sub(2) {
+                Some(i) if output[i] < u8::MAX && output[i + 1] == b => output[i] += 1,
+                _ => output.extend([1, b]),
+            }
+        }
+        output
+    }
+
+    fn decompress(input: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+        let mut output = vec![];
+        for run in input.chunks(2) {
+            let &[count, b] = run else {
+                return Err(DecodeError::Other("odd run-length input"));
+            };
+            if max_size < output.len() + usize::from(count) {
+                return Err(DecodeError::Other("decompressed value too large"));
+            }
+            output.extend(std::iter::repeat_n(b, count.into()));
+        }
+        Ok(output)
+    }
+}
+
+#[test]
+fn custom_compression() -> anyhow::Result<()> {
+    const TABLE: TableDefinition<u64, String, Lexicographical, Bincode, Compressed<RunLength>> =
+        TableDefinition::new("table");
+
+    let db = Database::in_memory()?;
+    let large = "a".repeat(1000);
+    db.write(|tx| {
+        let mut table = tx.open_table(&TABLE)?;
+        table.insert(&1, &large)?;
+        let raw = table.as_raw().get(&[1][..])?.expect("present");
+        assert!(raw.value().len() < 50);
+        assert_eq!(raw.value()[4], RunLength::TAG);
+        Ok::<_, Error>(())
+    })?;
+
+    let tx = db.begin_read()?;
+    assert_eq!(
+        tx.open_table(&TABLE)?.get(&1)?.map(|v| v.value()),
+        Some(large)
+    );
+    Ok(())
+}
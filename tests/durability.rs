@@ -0,0 +1,26 @@
+use redb_bincode::{Database, Durability, TableDefinition};
+
+const TABLE: TableDefinition<u64, u64> = TableDefinition::new("table");
+
+#[test]
+fn non_durable_then_immediate_commit() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    for i in 0..3 {
+        let mut tx = db.begin_write()?;
+        tx.set_durability(Durability::None);
+        tx.open_table(&TABLE)?.insert(&i, &i)?;
+        tx.commit()?;
+    }
+
+    let mut tx = db.begin_write()?;
+    tx.set_durability(Durability::Immediate);
+    tx.set_two_phase_commit(true);
+    tx.set_quick_repair(true);
+    tx.open_table(&TABLE)?.insert(&3, &3)?;
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    assert_eq!(tx.open_table(&TABLE)?.len()?, 4);
+    Ok(())
+}
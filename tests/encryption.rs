@@ -0,0 +1,112 @@
+use redb::ReadableTable as _;
+use redb_bincode::{Database, Error, TableDefinition};
+
+const SECRETS: TableDefinition<String, String> = TableDefinition::new("secrets").encrypted();
+
+#[test]
+fn encrypted_values() -> anyhow::Result<()> {
+    let db = Database::builder()
+        .set_encryption_key(&[7; 32])
+        .create_with_backend(redb::backends::InMemoryBackend::new())?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&SECRETS)?;
+        table.insert("alice", "hunter2")?;
+        assert_eq!(
+            table.insert("alice", "hunter3")?.map(|v| v.value()),
+            Some("hunter2".to_owned())
+        );
+
+        let raw = table.as_raw().first()?.expect("present").1.value().to_vec();
+        assert!(!raw.windows(b"hunter3".len()).any(|w| w == b"hunter3"));
+
+        let mut tampered = raw.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        table.as_raw_mut().insert(&[][..], &tampered[..])?;
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&SECRETS)?;
+    assert_eq!(
+        table.get("alice")?.map(|v| v.value()),
+        Some("hunter3".to_owned())
+    );
+    let first = table.first()?.expect("present").1;
+    assert!(matches!(first.value_try(), Err(Error::Decryption(_))));
+    Ok(())
+}
+
+#[test]
+fn ciphertexts_are_bound_to_table_and_key() -> anyhow::Result<()> {
+    const OTHER: TableDefinition<String, String> = TableDefinition::new("other").encrypted();
+
+    let db = Database::builder()
+        .set_encryption_key(&[7; 32])
+        .create_with_backend(redb::backends::InMemoryBackend::new())?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&SECRETS)?;
+        table.insert("alice", "hunter2")?;
+        table.insert("mallory", "letmein")?;
+        let raw = table
+            .as_raw()
+            .iter()?
+            .map(|e| e.map(|(k, v)| (k.value().to_vec(), v.value().to_vec())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let [(alice_key, alice_value), (mallory_key, _)] = &raw[..] else {
+            panic!("unexpected entries: {raw:?}");
+        };
+        table
+            .as_raw_mut()
+            .insert(&mallory_key[..], &alice_value[..])?;
+        assert!(matches!(
+            table.get("mallory")?.expect("present").value_try(),
+            Err(Error::Decryption(_))
+        ));
+
+        let mut other = tx.open_table(&OTHER)?;
+        other
+            .as_raw_mut()
+            .insert(&alice_key[..], &alice_value[..])?;
+        assert!(matches!(
+            other.get("alice")?.expect("present").value_try(),
+            Err(Error::Decryption(_))
+        ));
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[test]
+fn renaming_keeps_values_readable() -> anyhow::Result<()> {
+    const RENAMED: TableDefinition<String, String> = TableDefinition::new("renamed").encrypted();
+
+    let db = Database::builder()
+        .set_encryption_key(&[7; 32])
+        .create_with_backend(redb::backends::InMemoryBackend::new())?;
+
+    let tx = db.begin_write()?;
+    tx.open_table(&SECRETS)?.insert("alice", "hunter2")?;
+    tx.rename_table(&SECRETS, &RENAMED)?;
+    assert_eq!(
+        tx.open_table(&RENAMED)?.get("alice")?.map(|v| v.value()),
+        Some("hunter2".to_owned())
+    );
+    tx.commit()?;
+    Ok(())
+}
+
+#[test]
+fn encrypted_tables_need_a_cipher() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    assert!(matches!(
+        tx.open_table(&SECRETS).map(drop),
+        Err(Error::MissingCipher(name)) if name == "secrets"
+    ));
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use redb_bincode::{Database, Error, TableDefinition};
+
+const TABLE: TableDefinition<u64, String> = TableDefinition::new("table");
+
+#[test]
+fn try_retain_reports_bad_records() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&TABLE)?;
+        table.try_insert(&1, "one")?;
+        // `0xff` is not a valid varint length prefix for a `String`
+        table.as_raw_mut().insert(&[2u8][..], &[0xffu8][..])?;
+        table.try_insert(&3, "three")?;
+
+        assert!(matches!(table.try_get(&2)?, Some(v) if v.value_try().is_err()));
+        assert!(matches!(
+            table.try_retain(|_, _| false),
+            Err(Error::Decode(_))
+        ));
+        assert!(table.try_get(&1)?.is_none());
+        assert!(table.try_get(&3)?.is_some());
+    }
+    drop(tx);
+
+    Ok(())
+}
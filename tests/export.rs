@@ -0,0 +1,170 @@
+use redb_bincode::{Database, Error, MultimapTableDefinition, SortOrder, TableDefinition};
+
+const USERS: TableDefinition<u32, String> = TableDefinition::new("users");
+const POSTS: TableDefinition<u64, String> = TableDefinition::new("posts");
+const TAGS: MultimapTableDefinition<String, u64> = MultimapTableDefinition::new("tags");
+
+fn populated() -> anyhow::Result<Database> {
+    let db = Database::in_memory()?;
+    db.write(|tx| {
+        let mut users = tx.open_table(&USERS)?;
+        users.insert(&1, "a")?;
+        users.insert(&2, "b")?;
+        tx.open_table(&POSTS)?.insert(&10, "hello")?;
+        let mut tags = tx.open_multimap_table(&TAGS)?;
+        tags.insert("x", &10)?;
+        tags.insert("x", &11)?;
+        Ok::<_, Error>(())
+    })?;
+    Ok(db)
+}
+
+fn users(db: &Database) -> anyhow::Result<Vec<(u32, String)>> {
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&USERS)?;
+    Ok(table
+        .range::<u32>(..)?
+        .decoded()
+        .collect::<Result<_, _>>()?)
+}
+
+#[test]
+fn export_import_roundtrip() -> anyhow::Result<()> {
+    let db = populated()?;
+    let mut export = vec![];
+    db.export(&mut export)?;
+
+    let copy = Database::in_memory()?;
+    copy.import(&export[..])?;
+    assert_eq!(users(&copy)?, vec![(1, "a".into()), (2, "b".into())]);
+
+    let tx = copy.begin_read()?;
+    assert_eq!(
+        tx.open_table(&POSTS)?.get(&10)?.map(|v| v.value()),
+        Some("hello".into())
+    );
+    let tags = tx
+        .open_multimap_table(&TAGS)?
+        .get("x")?
+        .map(|v| v.map(|v| v.value()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(tags, vec![10, 11]);
+    Ok(())
+}
+
+#[test]
+fn export_selected_tables() -> anyhow::Result<()> {
+    let db = populated()?;
+    let mut export = vec![];
+    db.export_tables(&mut export, &[&USERS, &TAGS])?;
+
+    let copy = Database::in_memory()?;
+    copy.import(&export[..])?;
+    assert_eq!(users(&copy)?.len(), 2);
+
+    let tx = copy.begin_read()?;
+    assert!(tx.open_table(&POSTS).is_err());
+    assert_eq!(tx.open_multimap_table(&TAGS)?.get("x")?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn import_rejects_corruption() -> anyhow::Result<()> {
+    let db = populated()?;
+    let mut export = vec![];
+    db.export(&mut export)?;
+
+    let copy = Database::in_memory()?;
+    for i in 0..export.len() {
+        let mut corrupt = export.clone();
+        corrupt[i] ^= 0x01;
+        assert!(
+            matches!(copy.import(&corrupt[..]), Err(Error::CorruptExport(_))),
+            "byte {i}"
+        );
+    }
+    assert!(matches!(
+        copy.import(&export[..export.len() - 1]),
+        Err(Error::CorruptExport("truncated"))
+    ));
+
+    // nothing was committed
+    let tx = copy.begin_read()?;
+    assert!(tx.open_table(&USERS).is_err());
+    Ok(())
+}
+
+#[test]
+fn import_merges_into_existing_tables() -> anyhow::Result<()> {
+    let db = populated()?;
+    let mut export = vec![];
+    db.export_tables(&mut export, &[&USERS])?;
+
+    let copy = Database::in_memory()?;
+    copy.write(|tx| {
+        let mut users = tx.open_table(&USERS)?;
+        users.insert(&1, "old")?;
+        users.insert(&3, "c")?;
+        Ok::<_, Error>(())
+    })?;
+    copy.import(&export[..])?;
+    assert_eq!(
+        users(&copy)?,
+        vec![(1, "a".into()), (2, "b".into()), (3, "c".into())]
+    );
+    Ok(())
+}
+
+#[test]
+fn internal_tables_are_not_exported() -> anyhow::Result<()> {
+    let db = Database::builder()
+        .set_changelog(true)
+        .create_with_backend(redb::backends::InMemoryBackend::new())?;
+    db.write(|tx| {
+        tx.open_table(&USERS)?.insert(&1, "a")?;
+        Ok::<_, Error>(())
+    })?;
+    let mut export = vec![];
+    db.export(&mut export)?;
+
+    // the changelog can't record imports
+    assert!(matches!(db.import(&export[..]), Err(Error::NotLogged(_))));
+
+    let copy = Database::in_memory()?;
+    copy.import(&export[..])?;
+    let tx = copy.begin_read()?;
+    assert_eq!(tx.list_tables()?.collect::<Vec<_>>(), vec!["users"]);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Reversed;
+
+impl SortOrder for Reversed {
+    fn compare(data1: &[u8], data2: &[u8]) -> std::cmp::Ordering {
+        data2.cmp(data1)
+    }
+}
+
+#[test]
+fn custom_sort_orders_are_not_exported() -> anyhow::Result<()> {
+    const REVERSED: TableDefinition<u32, String, Reversed> = TableDefinition::new("reversed");
+
+    let db = populated()?;
+    db.write(|tx| {
+        let mut reversed = tx.open_table(&REVERSED)?;
+        reversed.insert(&1, "a")?;
+        reversed.insert(&2, "b")?;
+        Ok::<_, Error>(())
+    })?;
+
+    assert!(matches!(
+        db.export_tables(&mut vec![], &[&USERS, &REVERSED]),
+        Err(Error::UnsupportedSortOrder(name)) if name == "reversed"
+    ));
+    assert!(matches!(
+        db.export(&mut vec![]),
+        Err(Error::UnsupportedSortOrder(name)) if name == "reversed"
+    ));
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use redb_bincode::{Database, Error, TableDefinition};
+
+const TABLE: TableDefinition<u64, String> = TableDefinition::new("table");
+
+#[test]
+fn pop_extract_drain() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&TABLE)?;
+        assert!(table.is_empty()?);
+        for i in 0..10u64 {
+            table.insert(&i, &i.to_string())?;
+        }
+        assert_eq!(table.len()?, 10);
+
+        let first = table.pop_first()?.map(|(k, v)| (k.value(), v.value()));
+        assert_eq!(first, Some((0, "0".to_owned())));
+        let last = table.pop_last()?.map(|(k, _)| k.value());
+        assert_eq!(last, Some(9));
+
+        let odd = table
+            .extract_if(|k, _| k % 2 == 1)?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            odd.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            [1, 3, 5, 7]
+        );
+
+        let high = table
+            .extract_from_if(5.., |_, v| v != "8")?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(high, [(6, "6".to_owned())]);
+
+        let drained = table.drain(..5)?.rev().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(drained, [(4, "4".to_owned()), (2, "2".to_owned())]);
+
+        assert_eq!(table.len()?, 1);
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&TABLE)?;
+    assert_eq!(table.len()?, 1);
+    assert!(!table.is_empty()?);
+
+    Ok(())
+}
+
+#[test]
+fn extract_if_reports_undecodable_entries() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&TABLE)?;
+        table.insert(&0, "0")?;
+        table.insert(&2, "2")?;
+    }
+    tx.as_raw()
+        .open_table(redb::TableDefinition::<&[u8], &[u8]>::new("table"))?
+        .insert(&[1][..], &[0xff][..])?;
+    {
+        let mut table = tx.open_table(&TABLE)?;
+        // not selected, although the predicate never matches
+        let extracted = table.extract_if(|_, _| false)?.collect::<Vec<_>>();
+        assert_eq!(extracted.len(), 1);
+        assert!(matches!(extracted[0], Err(Error::Decode(_))));
+        assert_eq!(table.len()?, 3);
+
+        // the undecodable entry and those after it are kept
+        let drained = table.drain::<u64>(..)?.collect::<Vec<_>>();
+        assert_eq!(drained.len(), 2);
+        assert_eq!(drained[0].as_ref().ok(), Some(&(0, "0".to_owned())));
+        assert!(matches!(drained[1], Err(Error::Decode(_))));
+        assert_eq!(table.len()?, 2);
+
+        let drained = table.drain::<u64>(..)?.rev().collect::<Vec<_>>();
+        assert_eq!(drained.len(), 2);
+        assert_eq!(drained[0].as_ref().ok(), Some(&(2, "2".to_owned())));
+        assert!(matches!(drained[1], Err(Error::Decode(_))));
+        assert_eq!(table.len()?, 1);
+    }
+    tx.abort()?;
+    Ok(())
+}
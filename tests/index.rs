@@ -0,0 +1,190 @@
+use redb_bincode::{Database, Error, IndexDefinition, TableDefinition};
+
+#[derive(Debug, Clone, PartialEq, bincode::Encode, bincode::Decode)]
+struct User {
+    email: String,
+    age: u32,
+}
+
+const USERS: TableDefinition<u64, User> = TableDefinition::new("users");
+const USERS_BY_EMAIL: IndexDefinition<u64, User, String> =
+    IndexDefinition::new("users_by_email", |user: &User| user.email.clone()).unique();
+const USERS_BY_AGE: IndexDefinition<u64, User, u32> =
+    IndexDefinition::new("users_by_age", |user: &User| user.age);
+
+fn user(email: &str, age: u32) -> User {
+    User {
+        email: email.to_owned(),
+        age,
+    }
+}
+
+#[test]
+fn indexes_follow_modifications() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut users = tx.open_indexed_table(&USERS, &[&USERS_BY_EMAIL, &USERS_BY_AGE])?;
+        users.insert(&1, &user("a@x", 30))?;
+        users.insert(&2, &user("b@x", 30))?;
+        users.insert(&3, &user("c@x", 40))?;
+
+        // overwrite moves the index entries
+        let previous = users.insert(&2, &user("b2@x", 50))?;
+        assert_eq!(previous, Some(user("b@x", 30)));
+        assert!(users.get_by_index(&USERS_BY_EMAIL, "b@x")?.is_empty());
+
+        assert_eq!(users.remove(&3)?, Some(user("c@x", 40)));
+        assert_eq!(users.remove(&3)?, None);
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    {
+        let users = tx.open_indexed_table(&USERS, &[&USERS_BY_EMAIL, &USERS_BY_AGE])?;
+        assert_eq!(
+            users.get_by_index(&USERS_BY_EMAIL, "b2@x")?,
+            vec![(2, user("b2@x", 50))]
+        );
+        assert_eq!(
+            users.get_by_index(&USERS_BY_AGE, &30)?,
+            vec![(1, user("a@x", 30))]
+        );
+        assert_eq!(
+            users
+                .range_by_index(&USERS_BY_AGE, 0..)?
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(users.get_by_index(&USERS_BY_AGE, &40)?.is_empty());
+    }
+
+    let tx = db.begin_write()?;
+    {
+        let mut users = tx.open_indexed_table(&USERS, &[&USERS_BY_EMAIL, &USERS_BY_AGE])?;
+        users.retain(|_, u| u.age < 50)?;
+        assert!(users.get_by_index(&USERS_BY_EMAIL, "b2@x")?.is_empty());
+        assert!(users.get_by_index(&USERS_BY_AGE, &50)?.is_empty());
+        assert_eq!(users.as_table().len()?, 1);
+
+        let by_age = tx.open_multimap_table(&USERS_BY_AGE.table());
+        // the index table is already open in this transaction
+        assert!(by_age.is_err());
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[test]
+fn unique_index_rejects_duplicates() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut users = tx.open_indexed_table(&USERS, &[&USERS_BY_EMAIL, &USERS_BY_AGE])?;
+        users.insert(&1, &user("a@x", 30))?;
+        // same key may keep its unique index key
+        users.insert(&1, &user("a@x", 31))?;
+
+        assert!(matches!(
+            users.insert(&2, &user("a@x", 20)),
+            Err(Error::UniqueIndex(name)) if name == "users_by_email"
+        ));
+        assert!(users.as_table().get(&2)?.is_none());
+        assert!(users.get_by_index(&USERS_BY_AGE, &20)?.is_empty());
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let by_email = tx.open_multimap_table(&USERS_BY_EMAIL.table())?;
+    let keys = by_email
+        .get("a@x")?
+        .map(|k| k.map(|k| k.value()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(keys, vec![1]);
+
+    Ok(())
+}
+
+#[test]
+fn unopened_index_is_an_error() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let users = tx.open_indexed_table(&USERS, &[&USERS_BY_EMAIL])?;
+        assert!(matches!(
+            users.get_by_index(&USERS_BY_AGE, &30),
+            Err(Error::IndexNotOpened(name)) if name == "users_by_age"
+        ));
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let users = tx.open_indexed_table(&USERS, &[&USERS_BY_EMAIL])?;
+    assert!(matches!(
+        users.range_by_index(&USERS_BY_AGE, 0..),
+        Err(Error::IndexNotOpened(name)) if name == "users_by_age"
+    ));
+    Ok(())
+}
+
+#[test]
+fn retain_reports_undecodable_entries() -> anyhow::Result<()> {
+    const RAW_USERS: TableDefinition<u64, String> = TableDefinition::new("users");
+
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut users = tx.open_indexed_table(&USERS, &[&USERS_BY_AGE])?;
+        users.insert(&1, &user("a@x", 30))?;
+        users.insert(&3, &user("c@x", 40))?;
+    }
+    tx.open_table(&RAW_USERS)?.insert(&2, "not a user")?;
+    {
+        let mut users = tx.open_indexed_table(&USERS, &[&USERS_BY_AGE])?;
+        assert!(matches!(users.retain(|_, _| false), Err(Error::Decode(_))));
+        // removed before the failing entry, kept from it on
+        assert!(users.get_by_index(&USERS_BY_AGE, &30)?.is_empty());
+        assert_eq!(
+            users.get_by_index(&USERS_BY_AGE, &40)?,
+            vec![(3, user("c@x", 40))]
+        );
+    }
+    assert_eq!(
+        tx.open_table(&RAW_USERS)?.get(&2)?.map(|v| v.value()),
+        Some("not a user".to_owned())
+    );
+    tx.abort()?;
+    Ok(())
+}
+
+#[test]
+fn undecodable_previous_values_change_nothing() -> anyhow::Result<()> {
+    const RAW_USERS: TableDefinition<u64, String> = TableDefinition::new("users");
+
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    tx.open_table(&RAW_USERS)?.insert(&1, "not a user")?;
+    {
+        let mut users = tx.open_indexed_table(&USERS, &[&USERS_BY_AGE])?;
+        assert!(matches!(
+            users.insert(&1, &user("a@x", 30)),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(users.remove(&1), Err(Error::Decode(_))));
+        assert!(users.get_by_index(&USERS_BY_AGE, &30)?.is_empty());
+    }
+    assert_eq!(
+        tx.open_table(&RAW_USERS)?.get(&1)?.map(|v| v.value()),
+        Some("not a user".to_owned())
+    );
+    tx.abort()?;
+    Ok(())
+}
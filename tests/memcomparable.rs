@@ -0,0 +1,152 @@
+use proptest::prelude::*;
+use redb_bincode::memcomparable::{Decode, Encode};
+use redb_bincode::{
+    CodecDecode, Database, DecodeError, Lexicographical, Memcomparable, TableDefinition,
+};
+
+fn encoded<T: Encode + ?Sized>(v: &T) -> Vec<u8> {
+    let mut buf = vec![];
+    v.encode(&mut buf);
+    buf
+}
+
+fn check_order<T>(a: T, b: T) -> Result<(), TestCaseError>
+where
+    T: Encode + Decode + Ord + std::fmt::Debug,
+{
+    let (ea, eb) = (encoded(&a), encoded(&b));
+    prop_assert_eq!(ea.cmp(&eb), a.cmp(&b));
+    prop_assert_eq!(T::decode(&mut ea.as_slice())?, a);
+    prop_assert_eq!(T::decode(&mut eb.as_slice())?, b);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Id {
+    User(u64),
+    Group(String, Option<i32>),
+}
+
+impl Encode for Id {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Id::User(id) => (0u32, id).encode(buf),
+            Id::Group(name, n) => (1u32, name, n).encode(buf),
+        }
+    }
+}
+
+impl Decode for Id {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(match u32::decode(input)? {
+            0 => Id::User(Decode::decode(input)?),
+            1 => Id::Group(Decode::decode(input)?, Decode::decode(input)?),
+            _ => return Err(DecodeError::Other("invalid variant")),
+        })
+    }
+}
+
+fn id() -> impl Strategy<Value = Id> {
+    prop_oneof![
+        any::<u64>().prop_map(Id::User),
+        (".*", any::<Option<i32>>()).prop_map(|(s, n)| Id::Group(s, n)),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn order_signed(a: i64, b: i64) {
+        check_order(a, b)?;
+    }
+
+    #[test]
+    fn order_small_ints(a: (i8, u16, i128), b: (i8, u16, i128)) {
+        check_order(a, b)?;
+    }
+
+    #[test]
+    fn order_unsigned(a: u64, b: u64) {
+        check_order(a, b)?;
+    }
+
+    #[test]
+    fn order_strings(a: String, b: String) {
+        check_order(a, b)?;
+    }
+
+    #[test]
+    fn order_bytes(a: Vec<u8>, b: Vec<u8>) {
+        check_order(a, b)?;
+    }
+
+    // Small alphabet with `\0` makes shared prefixes and escapes likely
+    #[test]
+    fn order_tuples(a in ("[a\0b]{0,4}", any::<u8>()), b in ("[a\0b]{0,4}", any::<u8>())) {
+        check_order(a, b)?;
+    }
+
+    #[test]
+    fn order_options(a: (Option<String>, Option<i16>), b: (Option<String>, Option<i16>)) {
+        check_order(a, b)?;
+    }
+
+    #[test]
+    fn order_enums(a in id(), b in id()) {
+        check_order(a, b)?;
+    }
+}
+
+#[test]
+fn table_range_follows_ord() -> anyhow::Result<()> {
+    const TABLE: TableDefinition<(String, i64), u32, Lexicographical, Memcomparable> =
+        TableDefinition::new("table");
+
+    let db = Database::in_memory()?;
+
+    let keys = [
+        ("b".to_string(), -1),
+        ("aa".to_string(), 5),
+        ("b".to_string(), 1),
+        ("b".to_string(), i64::MIN),
+        ("a".to_string(), 0),
+    ];
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&TABLE)?;
+        for (i, k) in keys.iter().enumerate() {
+            table.insert(k, &(i as u32))?;
+        }
+    }
+    tx.commit()?;
+
+    let mut expected = keys.to_vec();
+    expected.sort();
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&TABLE)?;
+    let all = table
+        .range::<(String, i64)>(..)?
+        .keys()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(all, expected);
+
+    let last = table.range::<(String, i64)>(..)?.decoded().next_back();
+    assert_eq!(last.transpose()?, Some((("b".to_string(), 1), 2)));
+
+    let from_b = table
+        .range(("b".to_string(), -1)..)?
+        .keys()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(from_b, vec![("b".to_string(), -1), ("b".to_string(), 1)]);
+    Ok(())
+}
+
+#[test]
+fn codec_rejects_trailing_bytes() -> anyhow::Result<()> {
+    let mut bytes = encoded(&7u32);
+    assert_eq!(<Memcomparable as CodecDecode<u32>>::decode(&bytes)?, 7);
+    bytes.push(0);
+    assert!(<Memcomparable as CodecDecode<u32>>::decode(&bytes).is_err());
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use redb_bincode::{Database, MultimapTableDefinition};
+
+const INDEX: MultimapTableDefinition<String, u64> = MultimapTableDefinition::new("index");
+
+#[test]
+fn multimap_roundtrip() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_multimap_table(&INDEX)?;
+        for v in [3, 1, 2] {
+            assert!(!table.insert("a", &v)?);
+        }
+        assert!(table.insert("a", &1)?);
+        table.insert("b", &7)?;
+
+        assert!(table.remove("a", &3)?);
+        assert!(!table.remove("a", &3)?);
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    {
+        let table = tx.open_multimap_table(&INDEX)?;
+        let a = table
+            .get("a")?
+            .map(|v| v.map(|v| v.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(a, vec![1, 2]);
+
+        let keys = table
+            .range::<str>(..)?
+            .map(|res| res.map(|(k, values)| (k.value(), values.len())))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(keys, vec![("a".to_string(), 2), ("b".to_string(), 1)]);
+    }
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_multimap_table(&INDEX)?;
+        let removed = table
+            .remove_all("a")?
+            .map(|v| v.map(|v| v.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(removed, vec![1, 2]);
+        assert!(table.get("a")?.is_empty());
+    }
+    tx.commit()?;
+
+    Ok(())
+}
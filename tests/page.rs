@@ -0,0 +1,97 @@
+use redb_bincode::{Cursor, Database, Direction, Error, ReadableTable, TableDefinition};
+
+const ITEMS: TableDefinition<u32, String> = TableDefinition::new("items");
+
+#[test]
+fn paginate_across_transactions() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut items = tx.open_table(&ITEMS)?;
+        for i in 0..7 {
+            items.insert(&i, &format!("item {i}"))?;
+        }
+    }
+    tx.commit()?;
+
+    let mut seen = vec![];
+    let mut cursor = None;
+    loop {
+        // every page in a new transaction, passing the cursor through bytes
+        let tx = db.begin_read()?;
+        let page = tx.open_table(&ITEMS)?.page(cursor, 3, Direction::Forward)?;
+        seen.extend(page.items.into_iter().map(|(k, _)| k));
+        let Some(next) = page.next else { break };
+        let bytes = bincode::encode_to_vec(&next, bincode::config::standard())?;
+        cursor =
+            Some(bincode::decode_from_slice::<Cursor, _>(&bytes, bincode::config::standard())?.0);
+    }
+    assert_eq!(seen, (0..7).collect::<Vec<_>>());
+
+    let tx = db.begin_read()?;
+    let items = tx.open_table(&ITEMS)?;
+    let page = items.page(None, 4, Direction::Backward)?;
+    assert_eq!(
+        page.items.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+        vec![6, 5, 4, 3]
+    );
+    assert_eq!(page.items[0].1, "item 6");
+    let page = items.page(page.next, 4, Direction::Backward)?;
+    assert_eq!(
+        page.items.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+        vec![2, 1, 0]
+    );
+    assert!(page.next.is_none());
+
+    // an exactly full last page has no next cursor
+    let page = items.page(None, 7, Direction::Forward)?;
+    assert_eq!(page.items.len(), 7);
+    assert!(page.next.is_none());
+
+    // resuming after a removed key
+    let cursor = items.page(None, 2, Direction::Forward)?.next;
+    drop(items);
+    drop(tx);
+    let tx = db.begin_write()?;
+    tx.open_table(&ITEMS)?.remove(&1)?;
+    tx.commit()?;
+    let tx = db.begin_read()?;
+    let page = tx.open_table(&ITEMS)?.page(cursor, 2, Direction::Forward)?;
+    assert_eq!(
+        page.items.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn page_limits() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut items = tx.open_table(&ITEMS)?;
+        for i in 0..3 {
+            items.insert(&i, &format!("item {i}"))?;
+        }
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let items = tx.open_table(&ITEMS)?;
+    assert!(matches!(
+        items.page(None, 0, Direction::Forward),
+        Err(Error::ZeroPageLimit)
+    ));
+
+    // everything at once
+    let page = items.page(None, usize::MAX, Direction::Backward)?;
+    assert_eq!(
+        page.items.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+        vec![2, 1, 0]
+    );
+    assert!(page.next.is_none());
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use redb_bincode::{Database, Lexicographical, Memcomparable, TableDefinition};
+
+const EVENTS: TableDefinition<(u64, u64), String> = TableDefinition::new("events");
+const TAGS: TableDefinition<(u8, String, i32), (), Lexicographical, Memcomparable> =
+    TableDefinition::new("tags");
+#[allow(clippy::type_complexity)]
+const CELLS: TableDefinition<(u8, u8, u8, u8, u8, u8, u8, u8), (), Lexicographical, Memcomparable> =
+    TableDefinition::new("cells");
+
+#[test]
+fn prefix_scans_tuple_keys() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut events = tx.open_table(&EVENTS)?;
+        // 250 and 251 straddle the single byte varint limit
+        for user in [1, 2, 250, 251, 300] {
+            for ts in [5, 1_000, 70_000] {
+                events.insert(&(user, ts), &format!("{user}/{ts}"))?;
+            }
+        }
+
+        let two = events
+            .prefix(&2)?
+            .map(|res| res.map(|(k, _)| k.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(two, vec![(2, 5), (2, 1_000), (2, 70_000)]);
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let events = tx.open_table(&EVENTS)?;
+    for user in [1, 2, 250, 251, 300] {
+        let values = events
+            .prefix(&user)?
+            .map(|res| res.map(|(_, v)| v.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(values.len(), 3, "user {user}");
+        assert!(values.iter().all(|v| v.starts_with(&format!("{user}/"))));
+    }
+    assert_eq!(events.prefix(&3)?.count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn prefix_scans_memcomparable_keys() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut tags = tx.open_table(&TAGS)?;
+        for (group, name) in [(1, "a"), (1, "ab"), (2, "a"), (255, "a"), (255, "b")] {
+            for n in [-1, 1] {
+                tags.insert(&(group, name.to_owned(), n), &())?;
+            }
+        }
+
+        let keys = |group: u8, name: Option<&str>| {
+            let res = match name {
+                Some(name) => tags.prefix(&(group, name.to_owned()))?,
+                None => tags.prefix(&group)?,
+            };
+            res.map(|res| res.map(|(k, _)| k.value()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        // "a" must not match "ab"
+        assert_eq!(
+            keys(1, Some("a"))?,
+            vec![(1, "a".to_owned(), -1), (1, "a".to_owned(), 1)]
+        );
+        assert_eq!(keys(1, None)?.len(), 4);
+        // the encoded prefix has no successor
+        assert_eq!(keys(255, None)?.len(), 4);
+        assert_eq!(keys(255, Some("b"))?.len(), 2);
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[test]
+fn prefix_scans_wide_keys() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut cells = tx.open_table(&CELLS)?;
+        for a in 0..2 {
+            for h in 0..3 {
+                cells.insert(&(a, 1, 2, 3, 4, 5, 6, h), &())?;
+            }
+        }
+        cells.insert(&(1, 1, 2, 3, 4, 5, 7, 0), &())?;
+
+        assert_eq!(cells.prefix(&1)?.count(), 4);
+        assert_eq!(cells.prefix(&(1, 1, 2, 3))?.count(), 4);
+        let keys = cells
+            .prefix(&(1, 1, 2, 3, 4, 5, 6))?
+            .map(|res| res.map(|(k, _)| k.value().7))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(keys, vec![0, 1, 2]);
+    }
+    tx.commit()?;
+
+    Ok(())
+}
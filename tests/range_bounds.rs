@@ -0,0 +1,93 @@
+use std::ops::Bound;
+
+use redb_bincode::{
+    Database, Lexicographical, Memcomparable, OwnedBound, PrefixBound, ReadableTable,
+    TableDefinition,
+};
+
+const NAMES: TableDefinition<String, u32, Lexicographical, Memcomparable> =
+    TableDefinition::new("names");
+const EVENTS: TableDefinition<(u8, u32), (), Lexicographical, Memcomparable> =
+    TableDefinition::new("events");
+
+fn keys<K, V>(
+    range: redb_bincode::Range<'_, K, V, redb_bincode::SortKey<Lexicographical>, Memcomparable>,
+) -> Vec<K>
+where
+    Memcomparable: redb_bincode::Codec<K>,
+{
+    range.map(|res| res.unwrap().0.value()).collect()
+}
+
+#[test]
+fn independent_bounds() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut names = tx.open_table(&NAMES)?;
+        for (i, name) in ["a", "b", "ba", "c"].into_iter().enumerate() {
+            names.insert(name, &(i as u32))?;
+        }
+        let mut events = tx.open_table(&EVENTS)?;
+        for group in [1, 2, 3, 255] {
+            for ts in [10, 20] {
+                events.insert(&(group, ts), &())?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let names = tx.open_table(&NAMES)?;
+    assert_eq!(
+        keys(names.range_between(Bound::Excluded("b"), ..)?),
+        vec!["ba", "c"]
+    );
+    assert_eq!(
+        keys(names.range_between(.., Bound::Included(&"b".to_owned()))?),
+        vec!["a", "b"]
+    );
+
+    let events = tx.open_table(&EVENTS)?;
+    // full key start, prefix end
+    assert_eq!(
+        keys(events.range_between(Bound::Included(&(1, 20)), PrefixBound::Included(&2))?),
+        vec![(1, 20), (2, 10), (2, 20)]
+    );
+    assert_eq!(
+        keys(events.range_between(PrefixBound::Excluded(&1), PrefixBound::Excluded(&3))?),
+        vec![(2, 10), (2, 20)]
+    );
+    assert_eq!(
+        keys(events.range_between(PrefixBound::Included(&255), ..)?),
+        vec![(255, 10), (255, 20)]
+    );
+    // nothing sorts after all keys with a prefix of `0xff` bytes
+    assert!(keys(events.range_between(PrefixBound::Excluded(&255), ..)?).is_empty());
+
+    // owned keys, built on the fly
+    let group = 1;
+    assert_eq!(
+        keys(events.range_between(
+            OwnedBound::Excluded((group, 10)),
+            OwnedBound::Included((group + 1, 10))
+        )?),
+        vec![(1, 20), (2, 10)]
+    );
+    assert_eq!(
+        keys(names.range_between(.., OwnedBound::Excluded("b".to_owned()))?),
+        vec!["a"]
+    );
+
+    // also through `ReadableTable`
+    fn count(table: &impl ReadableTable<(u8, u32), (), Lexicographical, Memcomparable>) -> usize {
+        table
+            .range_between(PrefixBound::Included(&2), PrefixBound::Included(&3))
+            .unwrap()
+            .count()
+    }
+    assert_eq!(count(&events), 4);
+
+    Ok(())
+}
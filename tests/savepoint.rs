@@ -0,0 +1,66 @@
+use redb_bincode::{Database, Error, TableDefinition};
+
+const TABLE: TableDefinition<u64, u64> = TableDefinition::new("table");
+
+#[test]
+fn restore_ephemeral_savepoint() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let mut tx = db.begin_write()?;
+    let savepoint = tx.ephemeral_savepoint()?;
+    tx.open_table(&TABLE)?.insert(&1, &1)?;
+    tx.restore_savepoint(&savepoint)?;
+    tx.open_table(&TABLE)?.insert(&2, &2)?;
+    tx.commit()?;
+
+    let mut other_tx = db.begin_write()?;
+    assert!(matches!(
+        other_tx.restore_savepoint(&savepoint),
+        Err(Error::Savepoint(redb::SavepointError::InvalidSavepoint))
+    ));
+    other_tx.abort()?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&TABLE)?;
+    assert!(table.get(&1)?.is_none());
+    assert_eq!(table.get(&2)?.map(|v| v.value()), Some(2));
+    Ok(())
+}
+
+#[test]
+fn restore_persistent_savepoint() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    tx.open_table(&TABLE)?.insert(&1, &1)?;
+    tx.commit()?;
+
+    let tx = db.begin_write()?;
+    let id = tx.persistent_savepoint()?;
+    tx.open_table(&TABLE)?.insert(&2, &2)?;
+    tx.commit()?;
+
+    let mut tx = db.begin_write()?;
+    assert_eq!(
+        tx.list_persistent_savepoints()?.collect::<Vec<_>>(),
+        vec![id]
+    );
+    let savepoint = tx.get_persistent_savepoint(id)?;
+    tx.restore_savepoint(&savepoint)?;
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&TABLE)?;
+    assert_eq!(table.get(&1)?.map(|v| v.value()), Some(1));
+    assert!(table.get(&2)?.is_none());
+    drop(table);
+    drop(tx);
+
+    let tx = db.begin_write()?;
+    assert!(tx.delete_persistent_savepoint(id)?);
+    tx.commit()?;
+    let tx = db.begin_write()?;
+    assert_eq!(tx.list_persistent_savepoints()?.count(), 0);
+    tx.abort()?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use redb_bincode::{Bincode, BincodeSerde, Database, TableDefinition};
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct User {
+    name: String,
+    tags: Vec<String>,
+}
+
+const USERS: TableDefinition<u64, User, redb_bincode::Lexicographical, Bincode, BincodeSerde> =
+    TableDefinition::new("users");
+
+#[test]
+fn serde_values() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let user = User {
+        name: "alice".into(),
+        tags: vec!["admin".into()],
+    };
+
+    let tx = db.begin_write()?;
+    tx.open_table(&USERS)?.insert(&1, &user)?;
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&USERS)?;
+    assert_eq!(table.get(&1)?.map(|v| v.value()), Some(user));
+    let values = table.range(..)?.values().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(values.len(), 1);
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::cmp::Ordering;
+
+use redb_bincode::{Database, Error, Memcomparable, SortOrder, TableDefinition};
+
+#[derive(Debug)]
+struct Reversed;
+
+impl SortOrder for Reversed {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data2.cmp(data1)
+    }
+}
+
+const TABLE: TableDefinition<u32, String, Reversed, Memcomparable> =
+    TableDefinition::new("reversed");
+
+#[test]
+fn custom_sort_order() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+    db.write(|tx| {
+        let mut table = tx.open_table(&TABLE)?;
+        for k in [3, 1, 300, 2] {
+            table.insert(&k, &k.to_string())?;
+        }
+        Ok::<_, Error>(())
+    })?;
+
+    let tx = db.begin_read()?;
+    let table = tx.open_table(&TABLE)?;
+    let all = table
+        .range::<u32>(..)?
+        .keys()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(all, vec![300, 3, 2, 1]);
+
+    // bounds follow the table's order too
+    let from_3 = table.range(3..)?.decoded().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        from_3,
+        vec![(3, "3".into()), (2, "2".into()), (1, "1".into())]
+    );
+
+    assert_eq!(table.first()?.map(|(k, _)| k.value()), Some(300));
+    assert_eq!(table.last()?.map(|(k, _)| k.value()), Some(1));
+    Ok(())
+}
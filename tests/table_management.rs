@@ -0,0 +1,39 @@
+use redb::TableError;
+use redb_bincode::{Database, Error, ReadableTable as _, TableDefinition};
+
+const TABLE: TableDefinition<u64, String> = TableDefinition::new("table");
+const RENAMED: TableDefinition<u64, String> = TableDefinition::new("renamed");
+
+#[test]
+fn list_rename_delete() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    assert!(matches!(
+        tx.open_table_read_only(&TABLE),
+        Err(Error::Table(TableError::TableDoesNotExist(_)))
+    ));
+    assert!(!tx.table_exists(&TABLE)?);
+    tx.open_table(&TABLE)?.insert(&1, "one")?;
+    tx.rename_table(&TABLE, &RENAMED)?;
+    assert_eq!(
+        tx.open_table_read_only(&RENAMED)?
+            .get(&1)?
+            .map(|v| v.value()),
+        Some("one".to_string())
+    );
+    tx.commit()?;
+
+    let tx = db.begin_read()?;
+    assert_eq!(tx.list_tables()?.collect::<Vec<_>>(), vec!["renamed"]);
+    assert!(tx.table_exists(&RENAMED)?);
+    drop(tx);
+
+    let tx = db.begin_write()?;
+    assert!(tx.delete_table(&RENAMED)?);
+    assert!(!tx.delete_table(&RENAMED)?);
+    assert_eq!(tx.list_tables()?.count(), 0);
+    tx.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use redb_bincode::{Database, NoPrevious, Versioned, VersionedTableDefinition, VersionedValue};
+
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+struct UserV1 {
+    name: String,
+}
+
+impl VersionedValue for UserV1 {
+    const VERSION: u32 = 1;
+    type Previous = NoPrevious;
+
+    fn upgrade(previous: NoPrevious) -> Self {
+        match previous {}
+    }
+}
+
+#[derive(Debug, PartialEq, bincode::Encode, bincode::Decode)]
+struct UserV2 {
+    name: String,
+    admin: bool,
+}
+
+impl VersionedValue for UserV2 {
+    const VERSION: u32 = 2;
+    type Previous = UserV1;
+
+    fn upgrade(previous: UserV1) -> Self {
+        Self {
+            name: previous.name,
+            admin: false,
+        }
+    }
+}
+
+const USERS_V1: VersionedTableDefinition<u64, UserV1> = VersionedTableDefinition::new("users");
+const USERS_V2: VersionedTableDefinition<u64, UserV2> = VersionedTableDefinition::new("users");
+
+#[test]
+fn upgrade_on_read_and_migrate() -> anyhow::Result<()> {
+    let db = Database::in_memory()?;
+
+    let tx = db.begin_write()?;
+    {
+        let mut table = tx.open_table(&USERS_V1)?;
+        table.insert(&1, &Versioned(UserV1 { name: "a".into() }))?;
+        table.insert(&2, &Versioned(UserV1 { name: "b".into() }))?;
+    }
+    {
+        let mut table = tx.open_table(&USERS_V2)?;
+        table.insert(
+            &2,
+            &Versioned(UserV2 {
+                name: "b".into(),
+                admin: true,
+            }),
+        )?;
+        assert_eq!(
+            table.get(&1)?.map(|v| v.value().into_inner()),
+            Some(UserV2 {
+                name: "a".into(),
+                admin: false
+            })
+        );
+        assert_eq!(table.migrate_all()?, 1);
+        assert_eq!(table.migrate_all()?, 0);
+    }
+    {
+        // Old code can't read values it does not know about
+        let table = tx.open_table(&USERS_V1)?;
+        assert!(table.get(&1)?.expect("present").value_try().is_err());
+    }
+    tx.commit()?;
+
+    Ok(())
+}